@@ -49,27 +49,222 @@ def download_remote_strategy(strategy_id: str) -> bytes:
         raise RuntimeError(f"failed to download strategy: {e}")
 
 
+def import_strategy_crypto() -> Any:
+    """
+    导入已编译的 rust 扩展模块 strategy_crypto，未安装时返回 None
+
+    从仓库根目录运行时，扩展的源码目录 strategy_crypto/ 会被当作空的命名空间包导入成功，
+    因此以扩展导出的符号判断是否真正安装。
+    """
+    try:
+        import strategy_crypto  # type: ignore
+    except ImportError:
+        return None
+    if not hasattr(strategy_crypto, "decrypt_bytes"):
+        return None
+    return strategy_crypto
+
+
+def load_device_key(strategy_crypto: Any) -> str:
+    """
+    加载本机设备私钥并返回设备公钥，服务端用该公钥封装下发的策略密钥
+
+    私钥默认保存在 ~/.bullet-trade/device.key（首次使用时生成），
+    可通过 STRATEGY_DEVICE_KEY 指定密钥字符串（如 file:<路径>）。
+    """
+    from pathlib import Path
+
+    spec = os.getenv("STRATEGY_DEVICE_KEY")
+    if not spec:
+        path = Path.home() / ".bullet-trade" / "device.key"
+        if not path.exists():
+            private_key, _ = strategy_crypto.generate_device_key()
+            path.parent.mkdir(parents=True, exist_ok=True)
+            fd = os.open(path, os.O_WRONLY | os.O_CREAT | os.O_EXCL, 0o600)
+            with os.fdopen(fd, "w") as f:
+                f.write(private_key)
+        spec = f"file:{path}"
+    strategy_crypto.set_device_key(spec)
+    return strategy_crypto.device_public_key(spec)
+
+
+def strategy_key_params(strategy_crypto: Any) -> Dict[str, str]:
+    """
+    请求策略密钥时附带的参数；未安装 strategy_crypto 时为空，服务端按旧协议返回 key_b64
+
+    device_public_key 为本机设备公钥，服务端用它封装密钥；machine_id 与一次性挑战 time_nonce
+    用于服务端签发给本机的时间凭证 time_token，读取不到机器标识时省略。
+    """
+    if strategy_crypto is None:
+        return {}
+    params = {"device_public_key": load_device_key(strategy_crypto)}
+    try:
+        params["machine_id"] = strategy_crypto.machine_id()
+        params["time_nonce"] = strategy_crypto.time_challenge()
+    except strategy_crypto.LicenseError as e:
+        log.warning(f"无法读取本机机器标识，服务端不会签发时间凭证: {e}")
+    return params
+
+
+def strategy_key_from_response(strategy_crypto: Any, data: Any) -> Optional[str]:
+    """
+    解析策略密钥响应，返回 sealed: 或 b64: 前缀的密钥字符串，响应中没有密钥时返回 None
+
+    服务端返回封装给本机的 sealed_key 时得到 sealed: 前缀，旧服务端返回 key_b64 时得到 b64: 前缀；
+    响应中的时间凭证 time_token 同时用于确认联网（见 record_server_time）。
+    """
+    if not isinstance(data, dict):
+        return None
+    if strategy_crypto is not None:
+        record_server_time(strategy_crypto, data.get("time_token"))
+    if data.get("sealed_key"):
+        # 只有本机设备私钥能解封，对称密钥不以明文出现在响应中
+        return f"sealed:{data['sealed_key']}"
+    if data.get("key_b64"):
+        return f"b64:{data['key_b64']}"
+    return None
+
+
 def fetch_remote_strategy_key(strategy_id: str) -> Optional[str]:
-    """从后端请求 per-strategy key（返回 base64 字符串）"""
+    """
+    从后端请求 per-strategy key，参数与响应见 strategy_key_params / strategy_key_from_response
+    """
     base = _get_strategy_server_base().rstrip("/")
     url = f"{base}/api/strategies/{strategy_id}/key"
     headers = {"Accept": "application/json"}
     token = os.getenv("STRATEGY_API_TOKEN") or os.getenv("BT_API_TOKEN")
     if token:
         headers["Authorization"] = f"Bearer {token}"
+    strategy_crypto = import_strategy_crypto()
+    params = strategy_key_params(strategy_crypto)
     try:
-        resp = requests.get(url, headers=headers, timeout=10)
+        resp = requests.get(url, headers=headers, params=params, timeout=10)
         resp.raise_for_status()
-        data = resp.json()
-        if isinstance(data, dict) and "key_b64" in data:
-            return data["key_b64"]
-        return None
+        return strategy_key_from_response(strategy_crypto, resp.json())
     except requests.exceptions.HTTPError as e:
         raise RuntimeError(f"HTTP error when fetching key: {e}")
     except Exception as e:
         raise RuntimeError(f"failed to fetch strategy key: {e}")
 
 
+def get_offline_grace(strategy_crypto: Any) -> Any:
+    """
+    本机的离线宽限期状态（strategy_crypto.OfflineGrace）
+
+    状态文件默认为 ~/.bullet-trade/license/grace.state，可通过 STRATEGY_GRACE_FILE 覆盖，
+    加密状态文件的安装密钥保存在其旁边的 .key 文件中；
+    宽限期默认 168 小时，可通过 STRATEGY_OFFLINE_GRACE_HOURS 覆盖。
+    """
+    from pathlib import Path
+
+    path = os.getenv("STRATEGY_GRACE_FILE") or str(
+        Path.home() / ".bullet-trade" / "license" / "grace.state"
+    )
+    hours = float(os.getenv("STRATEGY_OFFLINE_GRACE_HOURS", "168"))
+    return strategy_crypto.OfflineGrace(path, grace_seconds=int(hours * 3600))
+
+
+def configure_license_authorities(strategy_crypto: Any) -> bool:
+    """
+    按 STRATEGY_LICENSE_AUTHORITIES 配置授权公钥，返回是否已配置授权公钥
+
+    授权与吊销列表均由这组服务端公钥签发；配置后扩展的所有解密接口都要求提供授权。
+    """
+    authorities = os.getenv("STRATEGY_LICENSE_AUTHORITIES", "")
+    keys = [k.strip() for k in authorities.split(",") if k.strip()]
+    if keys:
+        strategy_crypto.set_license_authorities(keys)
+    return bool(strategy_crypto.license_authorities())
+
+
+def load_strategy_license(strategy_crypto: Any) -> Tuple[Any, Any]:
+    """
+    读取 STRATEGY_LICENSE 授权与本机离线宽限期状态，没有授权时返回 (None, None)
+
+    已配置授权公钥时扩展拒绝不带授权的解密，缺少授权直接报错，不退回普通解密。
+    """
+    authorities_configured = configure_license_authorities(strategy_crypto)
+    token = os.getenv("STRATEGY_LICENSE")
+    if not token:
+        if authorities_configured:
+            raise ValueError(
+                "已配置 STRATEGY_LICENSE_AUTHORITIES，解密策略必须通过 STRATEGY_LICENSE 提供授权"
+            )
+        return None, None
+    # 离线宽限期：取密钥时已用服务端时间凭证确认联网（见 record_server_time），
+    # 解密时检查距上次联网的时长与时钟回拨，授权过期时间按状态文件记录的可信时间判断
+    return strategy_crypto.License(token), get_offline_grace(strategy_crypto)
+
+
+def decrypt_remote_strategy(
+    strategy_crypto: Any, key: str, data: bytes, strategy_id: str, mode: str
+) -> bytes:
+    """
+    解密单文件远程策略，要求密文绑定到所请求的策略 ID
+
+    持有授权时由 Rust 校验授权的签名、机器绑定、策略与运行模式，不覆盖时拒绝解密；
+    兼容已分发的旧版无头密文。
+    """
+    strategy_license, grace = load_strategy_license(strategy_crypto)
+    if strategy_license is not None:
+        dec = strategy_crypto.decrypt_licensed(
+            key, data, strategy_license, strategy_id, mode, allow_unbound=True, grace=grace
+        )
+    else:
+        dec = strategy_crypto.decrypt_bytes_with_context(
+            key, data, {"strategy_id": strategy_id}, allow_unbound=True
+        )
+    return bytes(dec)
+
+
+def record_server_time(strategy_crypto: Any, time_token: Optional[str]) -> None:
+    """
+    用服务端签发给本机的时间凭证确认联网，重新开始计算离线宽限期
+
+    只采信服务端签名、响应本进程挑战的时间，本机时钟不参与。凭证用授权公钥校验（见 configure_license_authorities）；
+    没有凭证或校验失败时不更新状态。
+    """
+    if not time_token:
+        return
+    try:
+        configure_license_authorities(strategy_crypto)
+        get_offline_grace(strategy_crypto).record_online(time_token)
+    except Exception as e:
+        log.warning(f"服务端时间凭证无效，未更新离线宽限期: {e}")
+
+
+def refresh_revocation_list(strategy_crypto: Any, fetch: bool = True) -> None:
+    """
+    加载本地缓存的吊销列表，并在 fetch 为 True 时从服务端获取最新列表
+
+    缓存默认为 ~/.bullet-trade/license/revocations.token，可通过 STRATEGY_REVOCATION_CACHE 覆盖。
+    服务端不可达或返回的列表无效、比缓存更旧时沿用缓存。
+    """
+    from pathlib import Path
+
+    cache_path = os.getenv("STRATEGY_REVOCATION_CACHE") or str(
+        Path.home() / ".bullet-trade" / "license" / "revocations.token"
+    )
+    try:
+        strategy_crypto.load_cached_revocation_list(cache_path)
+    except Exception as e:
+        log.warning(f"吊销列表缓存无效，已忽略: {e}")
+    if not fetch:
+        return
+    base = _get_strategy_server_base().rstrip("/")
+    headers = {}
+    token = os.getenv("STRATEGY_API_TOKEN") or os.getenv("BT_API_TOKEN")
+    if token:
+        headers["Authorization"] = f"Bearer {token}"
+    try:
+        resp = requests.get(f"{base}/api/revocations", headers=headers, timeout=10)
+        resp.raise_for_status()
+        if resp.text.strip():
+            strategy_crypto.set_revocation_list(resp.text, cache_path=cache_path)
+    except Exception as e:
+        log.warning(f"更新吊销列表失败，沿用本地缓存: {e}")
+
+
 from .models import Context, Portfolio, Position, Trade, OrderStatus
 from .globals import g, log, reset_globals
 from .settings import (
@@ -127,6 +322,8 @@ class BacktestEngine:
         after_trading_end: Optional[Callable] = None,
         process_initialize: Optional[Callable] = None,
         strategy_params: Optional[Dict[str, Any]] = None,
+        strategy_blob: Optional[bytes] = None,
+        strategy_key: Optional[str] = None,
     ):
         """
         初始化回测引擎
@@ -149,8 +346,14 @@ class BacktestEngine:
             after_trading_end: 盘后调用的函数
             process_initialize: 实盘初始化函数
             strategy_params: 策略参数字典，将覆盖策略文件中的g.变量默认值
+            strategy_blob: 内存中的策略（单文件密文、策略包或明文源码），提供时不再读取 strategy_file
+            strategy_key: 解密密钥，提供时优先于远程密钥与 STRATEGY_KEY / STRATEGY_KEYS
         """
         self.strategy_file = strategy_file
+        self.strategy_blob = strategy_blob
+        self.strategy_key = strategy_key
+        # 远程策略授权校验使用的运行模式：backtest / paper / live
+        self.strategy_mode = "backtest"
         self.strategy_params = strategy_params or {}
         self.start_date = pd.to_datetime(start_date) if start_date else None
         self.end_date = pd.to_datetime(end_date) if end_date else None
@@ -227,8 +430,13 @@ class BacktestEngine:
             # download_remote_strategy / fetch_remote_strategy_key 已移动到模块级别
             strategy_path = Path(self.strategy_file)
             # support remote strategies: "remote://<strategy_id>"
+            is_remote = isinstance(self.strategy_file, str) and self.strategy_file.startswith(
+                "remote://"
+            )
             data = None
-            if isinstance(self.strategy_file, str) and self.strategy_file.startswith("remote://"):
+            if self.strategy_blob is not None:
+                data = self.strategy_blob
+            elif is_remote:
                 strategy_id = self.strategy_file.split("://", 1)[1]
                 try:
                     data = download_remote_strategy(strategy_id)
@@ -237,47 +445,138 @@ class BacktestEngine:
                     raise
             else:
                 data = strategy_path.read_bytes()
-            decrypted_source: Optional[str] = None
-
-            try:
-                # 尝试导入已编译的 rust 扩展模块 strategy_crypto（若未安装则跳过）
-                import strategy_crypto  # type: ignore
-
-                # Determine decryption key:
-                # - If using remote strategy, request per-strategy key from server
-                if isinstance(self.strategy_file, str) and self.strategy_file.startswith(
-                    "remote://"
-                ):
-                    strategy_id = self.strategy_file.split("://", 1)[1]
-                    key_resp = fetch_remote_strategy_key(strategy_id)
-                    # key_resp is base64 string
-                    key = key_resp or ""
-                else:
-                    key = os.getenv("STRATEGY_KEY", "") or ""
-                try:
-                    dec = strategy_crypto.decrypt_bytes(key, data)
-                    # pyo3 返回 bytes-like 对象
-                    if isinstance(dec, (bytes, bytearray)):
-                        decrypted_source = dec.decode("utf-8")
+            source: Optional[str] = None
+            bundle_importer = None
+
+            # 尝试导入已编译的 rust 扩展模块 strategy_crypto（未安装时保持原有加载流程）
+            strategy_crypto = import_strategy_crypto()
+            if strategy_crypto is None and is_remote:
+                raise ValueError("远程策略是加密的，需要安装 strategy_crypto 扩展才能加载")
+
+            if strategy_crypto is not None:
+                trusted_publishers = os.getenv("STRATEGY_TRUSTED_PUBLISHERS")
+                if trusted_publishers:
+                    # 只接受受信任发布者签名的密文，泄露的策略密钥无法用来伪造策略
+                    strategy_crypto.set_trusted_publishers(
+                        [k.strip() for k in trusted_publishers.split(",") if k.strip()]
+                    )
+                if configure_license_authorities(strategy_crypto):
+                    # 吊销列表加载后每次解密都会检查
+                    refresh_revocation_list(strategy_crypto, fetch=is_remote)
+                # 先用头部探测选择加载路径：容器格式密文必须解密；
+                # 旧版无头密文无法从头部识别，但不会是合法的 UTF-8 源码
+                looks_encrypted = (
+                    is_remote
+                    or strategy_crypto.is_encrypted(data)
+                    or strategy_crypto.is_bundle(data)
+                )
+                if not looks_encrypted:
+                    try:
+                        data.decode("utf-8")
+                    except UnicodeDecodeError:
+                        looks_encrypted = True
+
+                if looks_encrypted:
+                    # Determine decryption key:
+                    # - If using remote strategy, request per-strategy key from server
+                    expected_context: Optional[Dict[str, str]] = None
+                    if is_remote:
+                        strategy_id = self.strategy_file.split("://", 1)[1]
+                        # key_resp 为 sealed: 或 b64: 前缀的密钥字符串
+                        key_resp = self.strategy_key or fetch_remote_strategy_key(strategy_id)
+                        key = key_resp or ""
+                        # 远程策略必须绑定到所请求的策略 ID，防止密文被替换成其他策略
+                        expected_context = {"strategy_id": strategy_id}
                     else:
-                        try:
-                            decrypted_source = bytes(dec).decode("utf-8")
-                        except Exception:
-                            decrypted_source = None
-                except Exception as dec_err:
-                    log.debug(f"尝试使用 strategy_crypto 解密失败: {dec_err}")
-            except Exception:
-                # 无法导入 strategy_crypto，保持原有加载流程
-                pass
+                        key = self.strategy_key or os.getenv("STRATEGY_KEY", "") or ""
+                    if key.startswith("sealed:"):
+                        # 封装给本机的密钥由 Rust 在解析时用设备私钥解封
+                        load_device_key(strategy_crypto)
+                    try:
+                        if is_remote and not strategy_crypto.is_bundle(data):
+                            dec = decrypt_remote_strategy(
+                                strategy_crypto, key, data, strategy_id, self.strategy_mode
+                            )
+                        else:
+                            # 配置了授权公钥时必须持有授权，由 Rust 按密文绑定的策略 ID 校验
+                            strategy_license, grace = load_strategy_license(strategy_crypto)
+                            licensed = {}
+                            if strategy_license is not None:
+                                licensed = dict(
+                                    license=strategy_license, mode=self.strategy_mode, grace=grace
+                                )
+                            if strategy_crypto.is_bundle(data):
+                                # 多文件策略包：由 Rust 导入钩子按需解密各模块，源码不经过 Python 层；
+                                # 远程策略包同样必须绑定到所请求的策略 ID
+                                bundle_importer = strategy_crypto.BundleImporter(
+                                    strategy_crypto.Bundle(
+                                        data, key, context=expected_context, **licensed
+                                    )
+                                )
+                                dec = None
+                            elif os.getenv("STRATEGY_KEYS") and not self.strategy_key:
+                                # 密钥轮换：按密文头部的 key_id 从密钥环中选择密钥
+                                dec = strategy_crypto.Keyring.from_env().decrypt(data, **licensed)
+                            elif strategy_license is not None:
+                                context = strategy_crypto.inspect(data).get("context") or {}
+                                if "strategy_id" not in context:
+                                    raise ValueError("策略密文未绑定 strategy_id，无法校验授权")
+                                dec = strategy_crypto.decrypt_licensed(
+                                    key,
+                                    data,
+                                    strategy_license,
+                                    context["strategy_id"],
+                                    self.strategy_mode,
+                                    grace=grace,
+                                )
+                            else:
+                                dec = strategy_crypto.decrypt_bytes(key, data)
+                    except strategy_crypto.StrategyCryptoError as dec_err:
+                        raise ValueError(f"策略解密失败: {dec_err}") from dec_err
+                    try:
+                        # pyo3 返回 bytes-like 对象
+                        if dec is not None:
+                            source = bytes(dec).decode("utf-8")
+                    except UnicodeDecodeError as dec_err:
+                        raise ValueError(f"解密后的策略源码不是 UTF-8: {dec_err}") from dec_err
+                else:
+                    log.debug("策略文件为明文源码，跳过解密")
 
-            if decrypted_source is not None:
-                # 使用解密后的源码直接创建模块并执行（避免写临时文件）
+            if bundle_importer is None and source is None and self.strategy_blob is not None:
+                # 内存中的明文策略直接执行，不回退去读取 strategy_file
+                try:
+                    source = bytes(data).decode("utf-8")
+                except UnicodeDecodeError as e:
+                    raise ValueError("策略已加密，需要安装 strategy_crypto 扩展才能加载") from e
+
+            if bundle_importer is not None:
+                # 替换之前加载的策略包，避免旧包中的同名模块被优先导入；
+                # 旧包已导入的模块也要从 sys.modules 中移除，否则 import 会直接返回缓存的旧模块
+                sys.meta_path[:] = [
+                    finder
+                    for finder in sys.meta_path
+                    if not isinstance(finder, strategy_crypto.BundleImporter)
+                ]
+                for name, module in list(sys.modules.items()):
+                    loader = getattr(getattr(module, "__spec__", None), "loader", None)
+                    if isinstance(loader, strategy_crypto.BundleImporter):
+                        del sys.modules[name]
+                bundle_importer.install()
+                spec = bundle_importer.find_spec(bundle_importer.entry_point)
+                strategy_module = importlib.util.module_from_spec(spec)
+                sys.modules["strategy"] = strategy_module
+                sys.modules[spec.name] = strategy_module
+                # 注入全局变量和函数
+                self._inject_globals(strategy_module)
+                spec.loader.exec_module(strategy_module)
+            elif source is not None:
+                # 使用解密后的源码（或内存中的明文策略）直接创建模块并执行（避免写临时文件）
                 strategy_module = types.ModuleType("strategy")
                 sys.modules["strategy"] = strategy_module
                 # 注入全局变量和函数
                 self._inject_globals(strategy_module)
                 exec(
-                    compile(decrypted_source, str(strategy_path), "exec"), strategy_module.__dict__
+                    compile(source, str(strategy_path), "exec"), strategy_module.__dict__
                 )
             else:
                 # 回退到原有的基于文件的动态导入
@@ -169,8 +169,16 @@ class LiveEngine:
         now_provider: Optional[Callable[[], datetime]] = None,
         sleep_provider: Optional[Callable[[float], Awaitable[None]]] = None,
         strategy_params: Optional[Dict[str, Any]] = None,
+        strategy_blob: Optional[bytes] = None,
+        strategy_key: Optional[str] = None,
     ):
         self.strategy_path = Path(strategy_file).resolve()
+        # 内存中的加密策略：strategy_file 仅作为标识（如 remote://<id>），不要求本地存在
+        self.strategy_file = (
+            str(strategy_file) if strategy_blob is not None else str(self.strategy_path)
+        )
+        self.strategy_blob = strategy_blob
+        self.strategy_key = strategy_key
         self.broker_name = broker_name
         self.config = LiveConfig.load(live_config)
         self.strategy_params = strategy_params or {}
@@ -229,7 +237,7 @@ class LiveEngine:
         """
         启动 LiveEngine（同步封装）。
         """
-        if not self.strategy_path.exists():
+        if self.strategy_blob is None and not self.strategy_path.exists():
             print(f"✗ 策略文件不存在: {self.strategy_path}")
             return 1
 
@@ -274,12 +282,17 @@ class LiveEngine:
 
     async def _bootstrap(self) -> None:
         log.info("🧠 初始化 Live 引擎")
-        if not self.strategy_path.exists():
+        if self.strategy_blob is None and not self.strategy_path.exists():
             raise FileNotFoundError(f"策略文件不存在: {self.strategy_path}")
 
         self._strategy_loader = BacktestEngine(
-            strategy_file=str(self.strategy_path), strategy_params=self.strategy_params
+            strategy_file=self.strategy_file,
+            strategy_params=self.strategy_params,
+            strategy_blob=self.strategy_blob,
+            strategy_key=self.strategy_key,
         )
+        broker = (self.broker_name or get_broker_config().get("default") or "simulator").lower()
+        self._strategy_loader.strategy_mode = "paper" if broker == "simulator" else "live"
         self._strategy_loader.load_strategy()
         self.initialize_func = self._strategy_loader.initialize_func
         self.handle_data_func = self._strategy_loader.handle_data_func
@@ -1476,7 +1489,11 @@ class LiveEngine:
 
     def _compute_strategy_hash(self) -> Optional[str]:
         try:
-            data = self.strategy_path.read_bytes()
+            data = (
+                self.strategy_blob
+                if self.strategy_blob is not None
+                else self.strategy_path.read_bytes()
+            )
             return hashlib.md5(data).hexdigest()
         except Exception:
             return None
@@ -261,24 +261,44 @@ class APIClient:
             _LOGGER.error(error_msg)
             return False, error_msg
 
-    def get_strategy_key(self, strategy_id: str, token: Optional[str] = None) -> Tuple[bool, Any]:
+    def get_strategy_key(
+        self,
+        strategy_id: str,
+        token: Optional[str] = None,
+        device_public_key: Optional[str] = None,
+        machine_id: Optional[str] = None,
+        time_nonce: Optional[str] = None,
+    ) -> Tuple[bool, Any]:
         """
         获取策略密钥
 
         Args:
             strategy_id: 策略ID
             token: JWT token（如果不提供，使用session中的token）
+            device_public_key: 本机设备公钥，提供时服务端返回封装给本机的密钥
+            machine_id: 本机机器标识，提供时服务端同时返回签发给本机的时间凭证 time_token
+            time_nonce: 时间凭证挑战（strategy_crypto.time_challenge()），服务端签入 time_token 防止重放
 
         Returns:
             (success: bool, data: Any)
             - success: 获取是否成功
-            - data: 成功时返回包含密钥的字典{"key_b64": base64字符串}，失败时返回错误信息
+            - data: 成功时返回包含密钥的字典{"sealed_key": base64字符串}
+              （旧服务端为{"key_b64": base64字符串}），失败时返回错误信息
         """
         headers = {}
         if token:
             headers["Authorization"] = f"Bearer {token}"
-
-        return self._make_request("GET", f"/strategies/{strategy_id}/key", headers=headers)
+        params = {}
+        if device_public_key:
+            params["device_public_key"] = device_public_key
+        if machine_id:
+            params["machine_id"] = machine_id
+        if time_nonce:
+            params["time_nonce"] = time_nonce
+
+        return self._make_request(
+            "GET", f"/strategies/{strategy_id}/key", headers=headers, params=params
+        )
 
 
 class APIConfig:
@@ -721,63 +721,51 @@ class BacktestPage(QWidget):
             text = it.text()
             sid = text.split()[0]
             # 直接从服务器下载并解密策略，然后加载到编辑器（不使用 remote:// 协议）
+            def fetch_source(api_client) -> str:
+                success, encrypted = api_client.download_strategy(sid)
+                if not success:
+                    raise Exception(encrypted)
+                from bullet_trade.core.engine import (
+                    decrypt_remote_strategy,
+                    import_strategy_crypto,
+                    strategy_key_from_response,
+                    strategy_key_params,
+                )
+
+                strategy_crypto = import_strategy_crypto()
+                if strategy_crypto is None:
+                    raise Exception("解密远端策略需要安装 strategy_crypto 扩展")
+
+                # 服务端用本机设备公钥封装密钥，只有本机能解封
+                success, key_data = api_client.get_strategy_key(
+                    sid, **strategy_key_params(strategy_crypto)
+                )
+                if not success:
+                    raise Exception(key_data)
+                key = strategy_key_from_response(strategy_crypto, key_data)
+                if key is None:
+                    raise Exception("无法获取解密密钥")
+                if strategy_crypto.is_bundle(encrypted):
+                    raise Exception("多文件策略包只能在实盘页面或通过 remote:// 加载")
+                # 与 StrategyManager._decrypt_strategy 一致：校验密文绑定的策略 ID 与回测授权
+                plaintext = decrypt_remote_strategy(
+                    strategy_crypto, key, encrypted, sid, "backtest"
+                )
+                return plaintext.decode("utf-8")
+
             try:
                 decrypted_code = None
 
-                # 优先使用 auth_manager.api_client 下载并解密
                 if getattr(self, "auth_manager", None) and getattr(
                     self.auth_manager, "api_client", None
                 ):
-                    api_client = self.auth_manager.api_client
-                    success, encrypted = api_client.download_strategy(sid)
-                    if not success:
-                        raise Exception(encrypted)
-                    success, key_data = api_client.get_strategy_key(sid)
-                    if not success:
-                        raise Exception(key_data)
-                    key_b64 = key_data.get("key_b64") if isinstance(key_data, dict) else None
-                    if not key_b64:
-                        raise Exception("无法获取解密密钥")
-                    # 解密（与 StrategyManager._decrypt_strategy 兼容）
-                    import base64
-                    from cryptography.hazmat.primitives.ciphers.aead import AESGCM
-
-                    key = base64.b64decode(key_b64)
-                    if len(encrypted) < 12 + 16:
-                        raise Exception("加密数据格式异常")
-                    nonce = encrypted[:12]
-                    tag = encrypted[-16:]
-                    ciphertext = encrypted[12:-16]
-                    aesgcm = AESGCM(key)
-                    plaintext = aesgcm.decrypt(nonce, ciphertext + tag, None)
-                    decrypted_code = plaintext.decode("utf-8")
+                    decrypted_code = fetch_source(self.auth_manager.api_client)
                 elif getattr(self, "strategy_manager", None):
-                    # 使用 strategy_manager 下载（只验证，不返回代码）
+                    # 先经 strategy_manager 下载并验证，再取回密文在内存中解密
                     success = self.strategy_manager.download_strategy(sid)
                     if not success:
                         raise Exception("下载或验证策略失败")
-                    # 从 strategy_manager 获取加密数据和密钥
-                    if hasattr(self.strategy_manager, 'downloaded_strategies') and sid in self.strategy_manager.downloaded_strategies:
-                        # 使用已下载的加密数据
-                        import base64
-                        from cryptography.hazmat.primitives.ciphers.aead import AESGCM
-
-                        success, encrypted = api_client.download_strategy(sid)
-                        if not success:
-                            raise Exception("重新下载策略失败")
-                        success, key_data = api_client.get_strategy_key(sid)
-                        if not success:
-                            raise Exception("获取密钥失败")
-                        key_b64 = key_data.get("key_b64") if isinstance(key_data, dict) else None
-                        key = base64.b64decode(key_b64)
-                        nonce = encrypted[:12]
-                        tag = encrypted[-16:]
-                        ciphertext = encrypted[12:-16]
-                        aesgcm = AESGCM(key)
-                        plaintext = aesgcm.decrypt(nonce, ciphertext + tag, None)
-                        decrypted_code = plaintext.decode("utf-8")
-                    else:
-                        raise Exception("策略未下载，请先下载策略")
+                    decrypted_code = fetch_source(self.strategy_manager.auth_manager.api_client)
                 else:
                     raise Exception("无法下载策略：未提供 AuthManager 或 StrategyManager")
 
@@ -70,7 +70,8 @@ class LiveWorker(QThread):
         runtime_dir,
         log_dir,
         strategy_params=None,
-        decrypted_source=None,
+        strategy_blob=None,
+        strategy_key=None,
     ):
         super().__init__()
         self.strategy_file = strategy_file
@@ -78,7 +79,8 @@ class LiveWorker(QThread):
         self.runtime_dir = runtime_dir
         self.log_dir = log_dir
         self.strategy_params = strategy_params or {}
-        self.decrypted_source = decrypted_source
+        self.strategy_blob = strategy_blob
+        self.strategy_key = strategy_key
         self._running = True
 
     def run(self):
@@ -121,96 +123,40 @@ class LiveWorker(QThread):
             if self.runtime_dir:
                 overrides["runtime_dir"] = self.runtime_dir
 
-            import tempfile
-
-            temp_strategy_path = None
+            # 输出当前使用的数据提供者（以便调试 provider 选择问题）
             try:
-                # 如果提供了内存中解密源码，则写入临时文件并使用该文件（LiveEngine 需要文件路径）
-                if isinstance(self.decrypted_source, str) and self.decrypted_source:
-                    # 在策略代码前添加依赖预导入代码
-                    # 这样即使策略代码中有 `from jqdata import *` 也能正常工作
-                    preamble = """# 自动注入的依赖预导入代码
-try:
-    import jqdatasdk
-
-    # 为了支持 `from jqdata import *`，将 jqdatasdk 的所有公开属性导入到全局命名空间
-    # 这样策略代码可以直接使用 jqdatasdk 的所有函数
-    _jqdata_public_attrs = [name for name in dir(jqdatasdk) if not name.startswith('_')]
-    for _attr in _jqdata_public_attrs:
-        try:
-            globals()[_attr] = getattr(jqdatasdk, _attr)
-        except Exception:
-            pass
-
-    # 同时也提供 jqdata 和 jqdatasdk 别名
-    jqdata = jqdatasdk
-
-    # 导入其他常用依赖
-    import pandas as pd
-    import numpy as np
-    from datetime import datetime, timedelta, date
-
-except ImportError as e:
-    # 如果依赖不存在，忽略错误，让策略运行时报错
-    pass
-
-"""
-                    tf = tempfile.NamedTemporaryFile(
-                        delete=False,
-                        suffix=".py",
-                        prefix="remote_live_",
-                        mode="w",
-                        encoding="utf-8",
-                    )
-                    tf.write(preamble + self.decrypted_source)
-                    tf.flush()
-                    tf.close()
-                    temp_strategy_path = tf.name
-                    strategy_path = temp_strategy_path
-                    self.output.emit("已创建临时策略文件（包含依赖预注入）")
-                else:
-                    strategy_path = self.strategy_file
-
-                # 输出当前使用的数据提供者（以便调试 provider 选择问题）
-                try:
-                    from bullet_trade.data.api import get_data_provider
-
-                    prov = get_data_provider()
-                    pname = getattr(prov, "name", prov.__class__.__name__)
-                    cname = prov.__class__.__name__
-                    self.output.emit(f"LiveWorker 数据提供者类名: {cname}, name属性: {pname}")
-                    info_parts = [f"class={cname}", f"name={pname}"]
-                    cfg = getattr(prov, "config", None)
-                    if isinstance(cfg, dict):
-                        for key in ("host", "port", "token", "source", "data_dir"):
-                            if cfg.get(key) is not None:
-                                info_parts.append(f"{key}={cfg.get(key)}")
-                    self.output.emit(f"当前数据提供者详情: {', '.join(info_parts)}")
-                except Exception as e:
-                    self.output.emit(f"读取当前数据提供者失败: {e}")
-                    import traceback
-
-                    self.output.emit(f"读取数据提供者失败详情: {traceback.format_exc()}")
-
-                engine = LiveEngine(
-                    strategy_file=strategy_path,
-                    broker_name=self.broker_name,
-                    live_config=overrides or None,
-                    strategy_params=self.strategy_params,
-                )
-
-                self.output.emit("启动实盘引擎...")
-                exit_code = engine.run()
-                self.finished.emit(exit_code)
-            finally:
-                # 清理临时策略文件（如果创建）
-                try:
-                    if temp_strategy_path:
-                        import os
+                from bullet_trade.data.api import get_data_provider
+
+                prov = get_data_provider()
+                pname = getattr(prov, "name", prov.__class__.__name__)
+                cname = prov.__class__.__name__
+                self.output.emit(f"LiveWorker 数据提供者类名: {cname}, name属性: {pname}")
+                info_parts = [f"class={cname}", f"name={pname}"]
+                cfg = getattr(prov, "config", None)
+                if isinstance(cfg, dict):
+                    for key in ("host", "port", "token", "source", "data_dir"):
+                        if cfg.get(key) is not None:
+                            info_parts.append(f"{key}={cfg.get(key)}")
+                self.output.emit(f"当前数据提供者详情: {', '.join(info_parts)}")
+            except Exception as e:
+                self.output.emit(f"读取当前数据提供者失败: {e}")
+                import traceback
+
+                self.output.emit(f"读取数据提供者失败详情: {traceback.format_exc()}")
+
+            # 远端策略以密文形式交给引擎，由 strategy_crypto 在内存中解密加载，明文不落盘
+            engine = LiveEngine(
+                strategy_file=self.strategy_file,
+                broker_name=self.broker_name,
+                live_config=overrides or None,
+                strategy_params=self.strategy_params,
+                strategy_blob=self.strategy_blob,
+                strategy_key=self.strategy_key,
+            )
 
-                        os.remove(temp_strategy_path)
-                except Exception:
-                    pass
+            self.output.emit("启动实盘引擎...")
+            exit_code = engine.run()
+            self.finished.emit(exit_code)
         except Exception as e:
             error_msg = str(e)
             self.output.emit(f"错误: {error_msg}")
@@ -426,6 +372,17 @@ class LivePage(QWidget):
         )
         left_layout.addWidget(self.status_label)
 
+        # 远端策略授权的离线宽限期
+        self.grace_label = QLabel()
+        self.grace_label.setStyleSheet(
+            f"""
+            padding: 0 8px;
+            color: {COLORS['text_secondary']};
+        """
+        )
+        left_layout.addWidget(self.grace_label)
+        self._refresh_grace_status()
+
         left_layout.addStretch()  # 添加弹性空间，使内容靠上
 
         # 右侧区域：日志（占2/3）
@@ -577,51 +534,47 @@ class LivePage(QWidget):
                 return
             text = it.text()
             sid = text.split()[0]
-            # 直接从服务器下载并解密策略源码到内存，供实盘启动时注入使用
+            # 从服务器下载加密策略并保留在内存中，启动实盘时由引擎解密加载
             try:
-                decrypted_code = None
-                if getattr(self, "auth_manager", None) and getattr(
-                    self.auth_manager, "api_client", None
+                if not (
+                    getattr(self, "auth_manager", None)
+                    and getattr(self.auth_manager, "api_client", None)
                 ):
-                    api_client = self.auth_manager.api_client
-                    success, encrypted = api_client.download_strategy(sid)
-                    if not success:
-                        raise Exception(encrypted)
-                    success, key_data = api_client.get_strategy_key(sid)
-                    if not success:
-                        raise Exception(key_data)
-                    key_b64 = key_data.get("key_b64") if isinstance(key_data, dict) else None
-                    if not key_b64:
-                        raise Exception("无法获取解密密钥")
-                    import base64
-                    from cryptography.hazmat.primitives.ciphers.aead import AESGCM
-
-                    key = base64.b64decode(key_b64)
-                    if len(encrypted) < 12 + 16:
-                        raise Exception("加密数据格式异常")
-                    nonce = encrypted[:12]
-                    tag = encrypted[-16:]
-                    ciphertext = encrypted[12:-16]
-                    aesgcm = AESGCM(key)
-                    plaintext = aesgcm.decrypt(nonce, ciphertext + tag, None)
-                    decrypted_code = plaintext.decode("utf-8")
-                else:
                     raise Exception("无法下载策略：未提供 AuthManager 或 API 客户端")
+                api_client = self.auth_manager.api_client
+                success, encrypted = api_client.download_strategy(sid)
+                if not success:
+                    raise Exception(encrypted)
+                from bullet_trade.core.engine import (
+                    import_strategy_crypto,
+                    strategy_key_from_response,
+                    strategy_key_params,
+                )
 
-                if decrypted_code is not None:
-                    self.decrypted_strategy_source = decrypted_code
-                    self.strategy_file_edit.setText("")
-                    try:
-                        self.params_widget.load_strategy_params_from_source(decrypted_code)
-                    except Exception:
-                        self.params_widget._clear_params()
-                    show_info(
-                        self,
-                        "远端策略已下载并解密，已加载到内存（启动实盘时将直接注入执行）。",
-                    )
-                    dlg.accept()
-                else:
-                    raise Exception("解密后策略内容为空")
+                strategy_crypto = import_strategy_crypto()
+                if strategy_crypto is None:
+                    raise Exception("运行远端策略需要安装 strategy_crypto 扩展")
+
+                # 服务端用本机设备公钥封装密钥，只有本机能解封
+                success, key_data = api_client.get_strategy_key(
+                    sid, **strategy_key_params(strategy_crypto)
+                )
+                if not success:
+                    raise Exception(key_data)
+                # 响应中签发给本机的时间凭证重新开始计算离线宽限期，本机时钟不参与
+                key = strategy_key_from_response(strategy_crypto, key_data)
+                if key is None:
+                    raise Exception("无法获取解密密钥")
+                self.remote_strategy = (f"remote://{sid}", encrypted, key)
+                self._refresh_grace_status()
+                self.strategy_file_edit.setText("")
+                # 远端策略只在启动时由引擎解密，明文不回到界面，因此不预览参数
+                self.params_widget._clear_params()
+                show_info(
+                    self,
+                    "远端策略已下载到内存（启动实盘时将在内存中解密执行）。",
+                )
+                dlg.accept()
             except Exception as e:
                 show_warning(self, f"无法下载或解密远端策略: {e}", title="下载失败")
 
@@ -633,6 +586,25 @@ class LivePage(QWidget):
         refresh()
         dlg.exec()
 
+    def _refresh_grace_status(self):
+        """刷新离线宽限期剩余时间"""
+        try:
+            from bullet_trade.core.engine import get_offline_grace, import_strategy_crypto
+
+            strategy_crypto = import_strategy_crypto()
+            remaining = None
+            if strategy_crypto is not None:
+                remaining = get_offline_grace(strategy_crypto).remaining()
+        except Exception:
+            remaining = None
+        if remaining is None:
+            self.grace_label.setText("")
+        elif remaining <= 0:
+            self.grace_label.setText("离线宽限期已用尽，请联网后重新选择远端策略")
+        else:
+            hours, rest = divmod(remaining, 3600)
+            self.grace_label.setText(f"离线宽限期剩余: {hours} 小时 {rest // 60} 分钟")
+
     def _on_strategy_file_changed(self):
         """策略文件改变时的处理"""
         strategy_file = self.strategy_file_edit.text().strip()
@@ -795,11 +767,14 @@ class LivePage(QWidget):
 
         # 验证参数
         strategy_file = self.strategy_file_edit.text().strip()
-        # 支持内存中解密后的策略：如果没有本地文件，但存在 decrypted_strategy_source 则允许
+        # 支持内存中的远端加密策略：如果没有本地文件，但已从远端加载策略则使用远端策略
+        strategy_blob = strategy_key = None
         if not strategy_file or not Path(strategy_file).exists():
-            if not getattr(self, "decrypted_strategy_source", None):
+            remote = getattr(self, "remote_strategy", None)
+            if not remote:
                 show_warning(self, "请选择有效的策略文件或先从远端加载策略", title="错误")
                 return
+            strategy_file, strategy_blob, strategy_key = remote
 
         # 获取策略参数
         strategy_params = self.params_widget.get_params()
@@ -811,7 +786,8 @@ class LivePage(QWidget):
             runtime_dir=self.runtime_dir_edit.text().strip() or None,
             log_dir=self.log_dir_edit.text().strip() or None,
             strategy_params=strategy_params,
-            decrypted_source=getattr(self, "decrypted_strategy_source", None),
+            strategy_blob=strategy_blob,
+            strategy_key=strategy_key,
         )
 
         self.worker.output.connect(self._append_log)
@@ -33,6 +33,7 @@ class StrategyManager:
         """
         self.auth_manager = auth_manager
         self.downloaded_strategies: Dict[str, str] = {}  # 缓存已下载的策略
+        self.strategy_details: Dict[str, Dict] = {}  # 已下载策略的密文头部信息（用于界面展示）
 
         # UI回调函数
         self.on_status_update: Optional[Callable[[str, str], None]] = None
@@ -160,8 +161,17 @@ class StrategyManager:
             if self.on_progress_message:
                 self.on_progress_message("正在获取解密密钥...")
 
-            # 获取解密密钥
-            success, key_data = self.auth_manager.api_client.get_strategy_key(strategy_id)
+            # 获取解密密钥：安装了 strategy_crypto 时服务端用本机设备公钥封装密钥，只有本机能解封
+            from bullet_trade.core.engine import (
+                import_strategy_crypto,
+                strategy_key_from_response,
+                strategy_key_params,
+            )
+
+            strategy_crypto = import_strategy_crypto()
+            success, key_data = self.auth_manager.api_client.get_strategy_key(
+                strategy_id, **strategy_key_params(strategy_crypto)
+            )
             if not success:
                 error_msg = f"获取密钥失败: {key_data}"
                 if self.on_status_update:
@@ -170,8 +180,8 @@ class StrategyManager:
                     self.on_error("下载失败", error_msg)
                 return False
 
-            key_b64 = key_data.get("key_b64")
-            if not key_b64:
+            key = strategy_key_from_response(strategy_crypto, key_data)
+            if key is None:
                 error_msg = "无法获取解密密钥"
                 if self.on_status_update:
                     self.on_status_update("下载失败", error_msg)
@@ -183,10 +193,13 @@ class StrategyManager:
                 self.on_progress_message("正在验证策略文件...")
 
             # 解密策略文件以验证完整性（不返回解密后的代码）
-            decrypted_code = self._decrypt_strategy(encrypted_data, key_b64)
+            decrypted_code = self._decrypt_strategy(encrypted_data, key, strategy_id, "backtest")
 
             # 只缓存策略ID，不缓存解密后的代码
             self.downloaded_strategies[strategy_id] = "[PROTECTED]"
+            details = self._inspect_strategy(encrypted_data)
+            if details is not None:
+                self.strategy_details[strategy_id] = details
 
             # 更新UI为成功状态
             if self.on_status_update:
@@ -216,21 +229,69 @@ class StrategyManager:
         """
         return strategy_id in self.downloaded_strategies
 
+    def get_strategy_details(self, strategy_id: str) -> Optional[Dict]:
+        """
+        获取已下载策略的密文信息（格式版本、算法、key_id、载荷长度、关联数据等）
+
+        Args:
+            strategy_id: 策略ID
+
+        Returns:
+            信息字典；旧版无头密文或未下载时返回 None
+        """
+        return self.strategy_details.get(strategy_id)
+
     def clear_download_cache(self):
         """清除下载缓存"""
         self.downloaded_strategies.clear()
+        self.strategy_details.clear()
         if self.on_status_update:
             self.on_status_update("未下载", "")
 
-    def _decrypt_strategy(self, encrypted_data: bytes, key_b64: str) -> str:
-        """解密策略文件"""
+    def _inspect_strategy(self, encrypted_data: bytes) -> Optional[Dict]:
+        """无需密钥解析密文头部"""
+        from bullet_trade.core.engine import import_strategy_crypto
+
+        strategy_crypto = import_strategy_crypto()
+        if strategy_crypto is None:
+            return None
+        if not strategy_crypto.is_encrypted(encrypted_data):
+            return None
+        try:
+            return strategy_crypto.inspect(encrypted_data)
+        except strategy_crypto.StrategyCryptoError as e:
+            _logger.debug(f"解析策略密文头部失败: {e}")
+            return None
+
+    def _decrypt_strategy(
+        self, encrypted_data: bytes, key: str, strategy_id: str, mode: str
+    ) -> str:
+        """
+        解密策略文件，并校验密文绑定的策略ID与请求一致；key 为 sealed: 或 b64: 前缀的密钥字符串
+
+        配置了授权公钥时按 STRATEGY_LICENSE 校验授权覆盖该策略与运行模式 mode
+        """
+        from bullet_trade.core.engine import decrypt_remote_strategy, import_strategy_crypto
+
+        strategy_crypto = import_strategy_crypto()
+        if strategy_crypto is not None:
+            try:
+                plaintext = decrypt_remote_strategy(
+                    strategy_crypto, key, encrypted_data, strategy_id, mode
+                )
+                return plaintext.decode("utf-8")
+            except Exception as e:
+                raise Exception(f"解密失败: {e}")
+
         try:
             # 使用高层AESGCM接口更可靠地处理nonce/tag等细节
             from cryptography.hazmat.primitives.ciphers.aead import AESGCM
             import base64
 
-            # 解密密钥
-            key = base64.b64decode(key_b64)
+            # 解密密钥；未安装 strategy_crypto 时不会请求封装密钥，只有 b64: 密钥
+            if not key.startswith("b64:"):
+                raise Exception("封装的密钥需要安装 strategy_crypto 才能解封")
+            key = base64.b64decode(key[len("b64:") :])
 
             # 最小长度：nonce(12) + tag(16)
             if len(encrypted_data) < 12 + 16:
@@ -0,0 +1,483 @@
+//! 多文件策略包
+//!
+//! 布局（多字节整数均为小端）：
+//!
+//! ```text
+//! magic(4) "BTSB" | version(1) | manifest_len(4) | manifest 密文 | 各条目密文...
+//! ```
+//!
+//! manifest 与每个条目都是独立的容器格式密文，manifest 明文记录包 ID、入口模块以及每个条目的
+//! 路径、在条目区中的偏移/长度和明文大小：
+//!
+//! ```text
+//! bundle_id(16) | entry_point_len(2) | entry_point | count(4)
+//! | { path_len(2) | path | offset(8) | len(8) | size(8) } * count
+//! ```
+//!
+//! 每个条目的关联数据绑定 `bundle_id` 与路径，条目无法在包之间或路径之间调换；
+//! 打包时传入的 `context`（如 strategy_id / version）同时写入清单和每个条目的关联数据，
+//! 解密时据此校验所请求的策略，吊销列表也按它匹配。
+//! 条目区的位置由经过认证的 manifest 给出，篡改或替换条目都会在读取时认证失败。
+//! 以 `.py` 结尾的条目为模块（`pkg/__init__.py` 对应包 `pkg`），其余为资源文件。
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use ed25519_dalek::SigningKey;
+use pyo3::exceptions::{PyKeyError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::{PyBytes, PyDict};
+use rand::rngs::OsRng;
+use rand::RngCore;
+use zeroize::Zeroizing;
+
+use crate::cipher::{open, seal_signed};
+use crate::compress::{Codec, Compression};
+use crate::container::{self, Algorithm, Context, Header, Reader};
+use crate::error::{MalformedCiphertextError, UnsupportedVersionError};
+use crate::files;
+use crate::grace::OfflineGrace;
+use crate::key::{parse_key, Key};
+use crate::license::{self, License};
+use crate::padding::Padding;
+use crate::secret::SecretBytes;
+use crate::sign::parse_signing_key;
+
+pub const BUNDLE_MAGIC: &[u8; 4] = b"BTSB";
+pub const BUNDLE_VERSION: u8 = 1;
+
+const BUNDLE_ID_LEN: usize = 16;
+/// magic + version + manifest_len
+const FIXED_LEN: usize = 4 + 1 + 4;
+
+/// 打包时跳过的目录
+const SKIPPED_DIRS: &[&str] = &["__pycache__"];
+
+/// 策略包自身使用的关联数据项，不能由调用方传入
+const RESERVED_CONTEXT_KEYS: &[&str] = &["bundle_id", "kind", "path"];
+
+struct Entry {
+    path: String,
+    offset: u64,
+    len: u64,
+    size: u64,
+}
+
+struct Manifest {
+    bundle_id: [u8; BUNDLE_ID_LEN],
+    entry_point: String,
+    entries: Vec<Entry>,
+}
+
+impl Manifest {
+    fn encode(&self) -> Vec<u8> {
+        let mut out = self.bundle_id.to_vec();
+        out.extend_from_slice(&(self.entry_point.len() as u16).to_le_bytes());
+        out.extend_from_slice(self.entry_point.as_bytes());
+        out.extend_from_slice(&(self.entries.len() as u32).to_le_bytes());
+        for entry in &self.entries {
+            out.extend_from_slice(&(entry.path.len() as u16).to_le_bytes());
+            out.extend_from_slice(entry.path.as_bytes());
+            out.extend_from_slice(&entry.offset.to_le_bytes());
+            out.extend_from_slice(&entry.len.to_le_bytes());
+            out.extend_from_slice(&entry.size.to_le_bytes());
+        }
+        out
+    }
+
+    fn decode(raw: &[u8]) -> PyResult<Self> {
+        let mut reader = Reader::new(raw, "策略包清单", MalformedCiphertextError::new_err);
+        let mut bundle_id = [0u8; BUNDLE_ID_LEN];
+        bundle_id.copy_from_slice(reader.take(BUNDLE_ID_LEN)?);
+        let entry_point = reader.string()?;
+        let count = u32::from_le_bytes(reader.array()?);
+        let mut entries = Vec::new();
+        for _ in 0..count {
+            entries.push(Entry {
+                path: reader.string()?,
+                offset: u64::from_le_bytes(reader.array()?),
+                len: u64::from_le_bytes(reader.array()?),
+                size: u64::from_le_bytes(reader.array()?),
+            });
+        }
+        Ok(Manifest {
+            bundle_id,
+            entry_point,
+            entries,
+        })
+    }
+}
+
+/// 条目路径对应的模块名；不是 `.py` 文件时返回 `None`
+pub fn module_name(path: &str) -> Option<String> {
+    let stem = path.strip_suffix(".py")?;
+    let stem = stem
+        .strip_suffix("/__init__")
+        .or_else(|| (stem == "__init__").then_some(""))
+        .unwrap_or(stem);
+    (!stem.is_empty()).then(|| stem.replace('/', "."))
+}
+
+/// 条目或清单的关联数据：调用方绑定的 `bound` 加上包 ID、类型与路径
+fn bundle_context(bundle_id: &[u8], path: Option<&str>, bound: &Context) -> Context {
+    let mut context = bound.clone();
+    context.insert("bundle_id".to_string(), hex::encode(bundle_id));
+    match path {
+        Some(path) => {
+            context.insert("kind".to_string(), "entry".to_string());
+            context.insert("path".to_string(), path.to_string());
+        }
+        None => {
+            context.insert("kind".to_string(), "manifest".to_string());
+        }
+    }
+    context
+}
+
+/// 按相对路径（`/` 分隔）收集目录下的文件，跳过隐藏文件、`__pycache__` 与 `.pyc`
+fn collect_entries(root: &Path) -> PyResult<BTreeMap<String, PathBuf>> {
+    let mut paths = Vec::new();
+    let skip = |name: &str| {
+        name.starts_with('.') || name.ends_with(".pyc") || SKIPPED_DIRS.contains(&name)
+    };
+    files::collect_files(root, true, &skip, &mut paths)?;
+    let mut out = BTreeMap::new();
+    for path in paths {
+        let rel = path.strip_prefix(root).unwrap_or(&path);
+        let rel: Option<Vec<_>> = rel.iter().map(|c| c.to_str()).collect();
+        let Some(rel) = rel.map(|rel| rel.join("/")) else {
+            return Err(PyValueError::new_err(format!(
+                "文件名不是合法的 UTF-8: {}",
+                path.display()
+            )));
+        };
+        if rel.len() > u16::MAX as usize {
+            return Err(PyValueError::new_err(format!("路径过长: {rel}")));
+        }
+        out.insert(rel, path);
+    }
+    Ok(out)
+}
+
+/// 入口模块：显式指定时必须存在；否则要求顶层恰好只有一个模块
+fn resolve_entry_point(
+    files: &BTreeMap<String, PathBuf>,
+    entry_point: Option<&str>,
+) -> PyResult<String> {
+    let modules: Vec<_> = files.keys().filter_map(|p| module_name(p)).collect();
+    if let Some(entry_point) = entry_point {
+        if !modules.iter().any(|m| m == entry_point) {
+            return Err(PyValueError::new_err(format!(
+                "入口模块 {entry_point} 不在策略包中"
+            )));
+        }
+        return Ok(entry_point.to_string());
+    }
+    let top_level: Vec<_> = modules.iter().filter(|m| !m.contains('.')).collect();
+    match top_level.as_slice() {
+        [only] => Ok(only.to_string()),
+        _ => Err(PyValueError::new_err(
+            "无法确定入口模块，请通过 entry_point 指定",
+        )),
+    }
+}
+
+struct PackOptions {
+    context: Context,
+    alg: Algorithm,
+    key_id: Option<String>,
+    compression: Option<Codec>,
+    padding: Option<Padding>,
+    signing_key: Option<SigningKey>,
+}
+
+impl PackOptions {
+    fn seal(&self, key: &Key, context: Context, data: &[u8]) -> PyResult<Vec<u8>> {
+        let mut header = Header::new(self.alg, self.key_id.as_deref())?;
+        header.context = Some(context);
+        header.compression = self.compression.map(Compression::new);
+        header.padding = self.padding;
+        seal_signed(key, &header, data, self.signing_key.as_ref())
+    }
+}
+
+fn pack(
+    key: &Key,
+    files: &BTreeMap<String, PathBuf>,
+    entry_point: String,
+    options: &PackOptions,
+) -> PyResult<Vec<u8>> {
+    let mut bundle_id = [0u8; BUNDLE_ID_LEN];
+    OsRng.fill_bytes(&mut bundle_id);
+    let mut body = Vec::new();
+    let mut entries = Vec::with_capacity(files.len());
+    for (path, file) in files {
+        let data = Zeroizing::new(fs::read(file)?);
+        let context = bundle_context(&bundle_id, Some(path), &options.context);
+        let blob = options.seal(key, context, &data)?;
+        entries.push(Entry {
+            path: path.clone(),
+            offset: body.len() as u64,
+            len: blob.len() as u64,
+            size: data.len() as u64,
+        });
+        body.extend_from_slice(&blob);
+    }
+    let manifest = Manifest {
+        bundle_id,
+        entry_point,
+        entries,
+    };
+    let context = bundle_context(&bundle_id, None, &options.context);
+    let manifest = options.seal(key, context, &manifest.encode())?;
+    let manifest_len =
+        u32::try_from(manifest.len()).map_err(|_| PyValueError::new_err("策略包清单过大"))?;
+    let mut out = Vec::with_capacity(FIXED_LEN + manifest.len() + body.len());
+    out.extend_from_slice(BUNDLE_MAGIC);
+    out.push(BUNDLE_VERSION);
+    out.extend_from_slice(&manifest_len.to_le_bytes());
+    out.extend_from_slice(&manifest);
+    out.extend_from_slice(&body);
+    Ok(out)
+}
+
+/// 把目录打包为加密策略包并返回字节串。
+///
+/// 目录下的 `.py` 文件作为模块，其余文件作为资源；`entry_point` 为入口模块名，
+/// 省略时要求顶层恰好只有一个模块。`context` 为绑定到整个包的关联数据（如 strategy_id / version），
+/// 不能使用 `bundle_id`、`kind`、`path` 这几个保留项。
+/// 其余参数与 `encrypt_bytes` 相同，作用于清单和每个条目。
+#[pyfunction]
+#[pyo3(signature = (
+    dir,
+    key,
+    *,
+    entry_point = None,
+    context = None,
+    key_id = None,
+    algorithm = None,
+    compression = None,
+    padding = None,
+    signing_key = None,
+))]
+pub fn pack_bundle(
+    py: Python<'_>,
+    dir: PathBuf,
+    key: &str,
+    entry_point: Option<&str>,
+    context: Option<Context>,
+    key_id: Option<&str>,
+    algorithm: Option<&str>,
+    compression: Option<&str>,
+    padding: Option<&str>,
+    signing_key: Option<&str>,
+) -> PyResult<Py<PyBytes>> {
+    let key = parse_key(key)?;
+    let context = context.unwrap_or_default();
+    if let Some(reserved) = context
+        .keys()
+        .find(|k| RESERVED_CONTEXT_KEYS.contains(&k.as_str()))
+    {
+        return Err(PyValueError::new_err(format!(
+            "关联数据项 {reserved} 由策略包保留"
+        )));
+    }
+    let options = PackOptions {
+        context,
+        alg: Algorithm::from_name(algorithm)?,
+        key_id: key_id.map(str::to_string),
+        compression: Codec::from_name(compression)?,
+        padding: Padding::from_spec(padding)?,
+        signing_key: signing_key.map(parse_signing_key).transpose()?,
+    };
+    let files = collect_entries(&dir)?;
+    let entry_point = resolve_entry_point(&files, entry_point)?;
+    let out = py.allow_threads(|| pack(&key, &files, entry_point, &options))?;
+    Ok(PyBytes::new_bound(py, &out).unbind())
+}
+
+/// 无需密钥判断数据是否为加密策略包（只检查 magic）
+#[pyfunction]
+pub fn is_bundle(data: &[u8]) -> bool {
+    data.len() >= FIXED_LEN && data.starts_with(BUNDLE_MAGIC)
+}
+
+/// 已解密清单的策略包；条目在读取时才解密
+#[pyclass(module = "strategy_crypto")]
+pub struct Bundle {
+    data: Vec<u8>,
+    key: Key,
+    manifest: Manifest,
+    /// 打包时绑定到整个包的关联数据（不含包自身的保留项）
+    context: Context,
+    /// 条目区在 `data` 中的起始位置
+    body_start: usize,
+}
+
+impl Bundle {
+    /// 解密清单；给出 `expected` 时要求包绑定的关联数据包含这些项，与 `decrypt_bytes_with_context` 一致
+    pub fn open(data: Vec<u8>, key: Key, expected: Option<&Context>) -> PyResult<Self> {
+        if data.len() < FIXED_LEN || !data.starts_with(BUNDLE_MAGIC) {
+            return Err(MalformedCiphertextError::new_err("不是加密策略包"));
+        }
+        if data[4] != BUNDLE_VERSION {
+            return Err(UnsupportedVersionError::new_err(format!(
+                "不支持的策略包版本: {}",
+                data[4]
+            )));
+        }
+        let manifest_len = u32::from_le_bytes([data[5], data[6], data[7], data[8]]) as usize;
+        let body_start = FIXED_LEN
+            .checked_add(manifest_len)
+            .filter(|&end| end <= data.len())
+            .ok_or_else(|| MalformedCiphertextError::new_err("策略包清单被截断"))?;
+        let (header, raw) = open(&key, &data[FIXED_LEN..body_start])?;
+        let manifest = Manifest::decode(&raw)?;
+        let manifest_context = bundle_context(&manifest.bundle_id, None, &Context::new());
+        container::check_context(header.as_ref(), &manifest_context, false)?;
+        if let Some(expected) = expected {
+            container::check_context(header.as_ref(), expected, false)?;
+        }
+        let mut context = header.and_then(|h| h.context).unwrap_or_default();
+        context.retain(|k, _| !RESERVED_CONTEXT_KEYS.contains(&k.as_str()));
+        Ok(Bundle {
+            data,
+            key,
+            manifest,
+            context,
+            body_start,
+        })
+    }
+
+    fn entry(&self, path: &str) -> Option<&Entry> {
+        self.manifest.entries.iter().find(|e| e.path == path)
+    }
+
+    /// 解密条目到安全缓冲区，并校验其绑定的包 ID、路径与包的关联数据
+    pub fn read_entry(&self, path: &str) -> PyResult<SecretBytes> {
+        let entry = self
+            .entry(path)
+            .ok_or_else(|| PyKeyError::new_err(path.to_string()))?;
+        let start = usize::try_from(entry.offset)
+            .ok()
+            .and_then(|offset| offset.checked_add(self.body_start));
+        let end = start.and_then(|start| start.checked_add(usize::try_from(entry.len).ok()?));
+        let (Some(start), Some(end)) = (start, end.filter(|&end| end <= self.data.len())) else {
+            return Err(MalformedCiphertextError::new_err(format!(
+                "策略包条目超出范围: {path}"
+            )));
+        };
+        let (header, pt) = open(&self.key, &self.data[start..end])?;
+        let expected = bundle_context(&self.manifest.bundle_id, Some(path), &self.context);
+        container::check_context(header.as_ref(), &expected, false)?;
+        Ok(pt)
+    }
+
+    pub fn entry_point(&self) -> &str {
+        &self.manifest.entry_point
+    }
+
+    /// 模块名对应的条目路径及其是否为包；与 Python 一致，包优先于同名模块
+    pub fn find_module(&self, name: &str) -> Option<(&str, bool)> {
+        let mut found = None;
+        for entry in &self.manifest.entries {
+            if module_name(&entry.path).as_deref() != Some(name) {
+                continue;
+            }
+            if entry.path.ends_with("__init__.py") {
+                return Some((&entry.path, true));
+            }
+            found = Some((entry.path.as_str(), false));
+        }
+        found
+    }
+}
+
+#[pymethods]
+impl Bundle {
+    /// `context` 为期望包绑定的关联数据（如 `{"strategy_id": ...}`），不匹配时抛出 AuthenticationError。
+    ///
+    /// 配置了授权公钥时必须传入 `license` 与 `mode`，授权须覆盖包绑定的 strategy_id；
+    /// 传入 `grace` 时同时执行离线宽限期检查
+    #[new]
+    #[pyo3(signature = (data, key, *, context = None, license = None, mode = None, grace = None))]
+    fn new(
+        data: Vec<u8>,
+        key: &str,
+        context: Option<Context>,
+        license: Option<PyRef<'_, License>>,
+        mode: Option<&str>,
+        grace: Option<PyRef<'_, OfflineGrace>>,
+    ) -> PyResult<Self> {
+        let bundle = Bundle::open(data, parse_key(key)?, context.as_ref())?;
+        license::check_bound_license(
+            license.as_deref(),
+            mode,
+            grace.as_deref(),
+            Some(&bundle.context),
+        )?;
+        Ok(bundle)
+    }
+
+    #[staticmethod]
+    #[pyo3(signature = (path, key, *, context = None, license = None, mode = None, grace = None))]
+    fn from_file(
+        path: PathBuf,
+        key: &str,
+        context: Option<Context>,
+        license: Option<PyRef<'_, License>>,
+        mode: Option<&str>,
+        grace: Option<PyRef<'_, OfflineGrace>>,
+    ) -> PyResult<Self> {
+        Bundle::new(fs::read(path)?, key, context, license, mode, grace)
+    }
+
+    #[getter(entry_point)]
+    fn py_entry_point(&self) -> &str {
+        self.entry_point()
+    }
+
+    /// 打包时绑定的关联数据，未绑定时为空字典
+    #[getter]
+    fn context(&self) -> Context {
+        self.context.clone()
+    }
+
+    /// 条目列表：`{"path", "size", "module"}`，资源文件的 module 为 None
+    fn entries<'py>(&self, py: Python<'py>) -> PyResult<Vec<Bound<'py, PyDict>>> {
+        self.manifest
+            .entries
+            .iter()
+            .map(|entry| {
+                let info = PyDict::new_bound(py);
+                info.set_item("path", &entry.path)?;
+                info.set_item("size", entry.size)?;
+                info.set_item("module", module_name(&entry.path))?;
+                Ok(info)
+            })
+            .collect()
+    }
+
+    /// 解密并返回条目内容
+    fn read(&self, py: Python<'_>, path: &str) -> PyResult<Py<PyBytes>> {
+        let pt = self.read_entry(path)?;
+        Ok(PyBytes::new_bound(py, &pt).unbind())
+    }
+
+    fn __contains__(&self, path: &str) -> bool {
+        self.entry(path).is_some()
+    }
+
+    fn __len__(&self) -> usize {
+        self.manifest.entries.len()
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "Bundle(entry_point={:?}, entries={})",
+            self.manifest.entry_point,
+            self.manifest.entries.len()
+        )
+    }
+}
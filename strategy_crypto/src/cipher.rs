@@ -0,0 +1,266 @@
+//! AEAD 加解密核心，供各个 Python 接口共用
+//!
+//! AES-GCM、ChaCha20-Poly1305 等 AEAD 本身不具备密钥承诺性：可以构造出在多个密钥下都能
+//! 通过认证的密文，配合逐个尝试密钥的解密流程即可实施分区预言攻击。新密文因此不直接使用
+//! 调用方的密钥，而是以 nonce 为盐经 HKDF-SHA256 派生出加密子密钥和承诺值，承诺值写入头部；
+//! 解密时先比对承诺值，不一致即判定密钥错误，不会进入 AEAD 解密。
+//! 带发布者签名的密文在这之前还要通过签名校验，见 `sign` 模块。
+
+use aes_gcm::aead::generic_array::GenericArray;
+use aes_gcm::aead::{Aead, AeadInPlace, KeyInit, Payload};
+use aes_gcm::Aes256Gcm;
+use aes_gcm_siv::Aes256GcmSiv;
+use chacha20poly1305::{ChaCha20Poly1305, XChaCha20Poly1305};
+use ed25519_dalek::SigningKey;
+use hkdf::Hkdf;
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use rand::rngs::OsRng;
+use rand::RngCore;
+use sha2::Sha256;
+
+use crate::compress::{self, Compression};
+use crate::container::{self, Algorithm, Header, COMMIT_LEN, NONCE_LEN};
+use crate::error::{
+    AuthenticationError, InvalidKeyError, MalformedCiphertextError, StrategyCryptoError,
+};
+use crate::key::Key;
+use crate::padding;
+use crate::revocation;
+use crate::secret::{SecretBytes, SecretKey};
+use crate::sign;
+
+/// 承诺派生的 HKDF info，加密子密钥与承诺值使用不同标签
+const COMMIT_KEY_LABEL: &[u8] = b"bullet-trade/key-commit/v1/key";
+const COMMIT_TAG_LABEL: &[u8] = b"bullet-trade/key-commit/v1/commit";
+
+/// 按头部记录的算法分派的 AEAD 实例；各实现在 drop 时清零密钥状态
+pub enum AnyCipher {
+    // 展开后的 AES 轮密钥较大，装箱以免拖大整个枚举（GCM-SIV 同理）
+    Aes256Gcm(Box<Aes256Gcm>),
+    ChaCha20Poly1305(ChaCha20Poly1305),
+    XChaCha20Poly1305(XChaCha20Poly1305),
+    Aes256GcmSiv(Box<Aes256GcmSiv>),
+}
+
+impl AnyCipher {
+    pub fn new(alg: Algorithm, key: &Key) -> PyResult<Self> {
+        let key = &key[..];
+        let cipher = match alg {
+            Algorithm::Aes256Gcm => {
+                Aes256Gcm::new_from_slice(key).map(|c| AnyCipher::Aes256Gcm(Box::new(c)))
+            }
+            Algorithm::ChaCha20Poly1305 => {
+                ChaCha20Poly1305::new_from_slice(key).map(AnyCipher::ChaCha20Poly1305)
+            }
+            Algorithm::XChaCha20Poly1305 => {
+                XChaCha20Poly1305::new_from_slice(key).map(AnyCipher::XChaCha20Poly1305)
+            }
+            Algorithm::Aes256GcmSiv => {
+                Aes256GcmSiv::new_from_slice(key).map(|c| AnyCipher::Aes256GcmSiv(Box::new(c)))
+            }
+        };
+        cipher.map_err(|e| InvalidKeyError::new_err(e.to_string()))
+    }
+
+    pub fn encrypt(
+        &self,
+        nonce: &[u8],
+        payload: Payload<'_, '_>,
+    ) -> aes_gcm::aead::Result<Vec<u8>> {
+        match self {
+            AnyCipher::Aes256Gcm(c) => c.encrypt(GenericArray::from_slice(nonce), payload),
+            AnyCipher::ChaCha20Poly1305(c) => c.encrypt(GenericArray::from_slice(nonce), payload),
+            AnyCipher::XChaCha20Poly1305(c) => c.encrypt(GenericArray::from_slice(nonce), payload),
+            AnyCipher::Aes256GcmSiv(c) => c.encrypt(GenericArray::from_slice(nonce), payload),
+        }
+    }
+
+    pub fn decrypt_in_place(
+        &self,
+        nonce: &[u8],
+        aad: &[u8],
+        buf: &mut Vec<u8>,
+    ) -> aes_gcm::aead::Result<()> {
+        match self {
+            AnyCipher::Aes256Gcm(c) => {
+                c.decrypt_in_place(GenericArray::from_slice(nonce), aad, buf)
+            }
+            AnyCipher::ChaCha20Poly1305(c) => {
+                c.decrypt_in_place(GenericArray::from_slice(nonce), aad, buf)
+            }
+            AnyCipher::XChaCha20Poly1305(c) => {
+                c.decrypt_in_place(GenericArray::from_slice(nonce), aad, buf)
+            }
+            AnyCipher::Aes256GcmSiv(c) => {
+                c.decrypt_in_place(GenericArray::from_slice(nonce), aad, buf)
+            }
+        }
+    }
+}
+
+/// 由密钥和盐派生 (加密子密钥, 承诺值)；单条消息以 nonce 为盐，分块流使用独立的随机盐。
+/// HMAC-SHA256 抗碰撞，不同密钥几乎不可能得到相同的承诺值
+pub fn commit_key(key: &Key, salt: &[u8]) -> PyResult<(SecretKey, [u8; COMMIT_LEN])> {
+    let hk = Hkdf::<Sha256>::new(Some(salt), &key[..]);
+    let mut subkey = SecretKey::zeroed();
+    let mut commitment = [0u8; COMMIT_LEN];
+    hk.expand(COMMIT_KEY_LABEL, &mut subkey[..])
+        .and_then(|_| hk.expand(COMMIT_TAG_LABEL, &mut commitment))
+        .map_err(|e| InvalidKeyError::new_err(format!("HKDF 派生失败: {e}")))?;
+    Ok((subkey, commitment))
+}
+
+/// 校验头部记录的承诺值，通过后返回加密子密钥（承诺值本身公开，无需常数时间比较）
+pub fn verify_commitment(
+    key: &Key,
+    salt: &[u8],
+    expected: &[u8; COMMIT_LEN],
+) -> PyResult<SecretKey> {
+    let (subkey, commitment) = commit_key(key, salt)?;
+    if &commitment != expected {
+        return Err(AuthenticationError::new_err(
+            "认证失败：密钥与密文的密钥承诺不匹配",
+        ));
+    }
+    Ok(subkey)
+}
+
+fn auth_failed() -> PyErr {
+    AuthenticationError::new_err("认证失败：密钥错误或密文被篡改")
+}
+
+/// 原地解密到安全缓冲区，明文不会在普通堆内存中留下副本
+fn decrypt_in_place(
+    cipher: &AnyCipher,
+    nonce: &[u8],
+    aad: &[u8],
+    ct: &[u8],
+) -> PyResult<SecretBytes> {
+    let mut buf = SecretBytes::from_slice(ct);
+    cipher
+        .decrypt_in_place(nonce, aad, buf.as_vec_mut())
+        .map_err(|_| auth_failed())?;
+    Ok(buf)
+}
+
+/// 按头部加密，输出 头部 + nonce + ciphertext||tag；总是附带新的密钥承诺。
+///
+/// 头部带压缩参数时先压缩明文，原始长度由这里填写；带填充策略时再补齐到对应档位
+pub fn seal(key: &Key, header: &Header, plaintext: &[u8]) -> PyResult<Vec<u8>> {
+    if header.compression.is_some() && header.padding.is_some() {
+        // 头部公开记录了压缩前的长度，填充将失去意义
+        return Err(PyValueError::new_err("compression 与 padding 不能同时使用"));
+    }
+    let mut nonce = vec![0u8; header.nonce_len()];
+    OsRng.fill_bytes(&mut nonce);
+    let (subkey, commitment) = commit_key(key, &nonce)?;
+    let compressed = header
+        .compression
+        .as_ref()
+        .map(|c| compress::compress(c.codec, plaintext))
+        .transpose()?;
+    let header = Header {
+        commitment: Some(commitment),
+        compression: header.compression.as_ref().map(|c| Compression {
+            original_len: plaintext.len() as u64,
+            ..c.clone()
+        }),
+        ..header.clone()
+    };
+    let plaintext = compressed.as_deref().unwrap_or(plaintext);
+    let padded = header
+        .padding
+        .map(|p| padding::pad(p, plaintext))
+        .transpose()?;
+    let plaintext = padded.as_deref().unwrap_or(plaintext);
+    let cipher = AnyCipher::new(header.alg, &subkey)?;
+    let mut out = header.encode()?;
+    let ct = cipher
+        .encrypt(
+            &nonce,
+            Payload {
+                msg: plaintext,
+                aad: &out,
+            },
+        )
+        .map_err(|e| StrategyCryptoError::new_err(format!("加密失败: {e}")))?;
+    out.extend_from_slice(&nonce);
+    out.extend_from_slice(&ct);
+    Ok(out)
+}
+
+/// 与 `seal` 相同，提供发布者私钥时把公钥写入头部并在末尾追加签名
+pub fn seal_signed(
+    key: &Key,
+    header: &Header,
+    plaintext: &[u8],
+    signing_key: Option<&SigningKey>,
+) -> PyResult<Vec<u8>> {
+    let Some(signing_key) = signing_key else {
+        return seal(key, header, plaintext);
+    };
+    let header = Header {
+        signer: Some(signing_key.verifying_key().to_bytes()),
+        ..header.clone()
+    };
+    let mut out = seal(key, &header, plaintext)?;
+    sign::append_signature(signing_key, &mut out);
+    Ok(out)
+}
+
+/// 用已解析的头部解密容器，`header_len` 为 `Header::parse` 返回的头部长度。
+///
+/// 先校验发布者签名与吊销列表，再校验承诺值；容器格式必须带承诺，缺少时直接拒绝，
+/// 否则构造的无承诺密文可以在逐个尝试密钥时充当分区预言。
+pub fn open_container(
+    key: &Key,
+    blob: &[u8],
+    header: &Header,
+    header_len: usize,
+) -> PyResult<SecretBytes> {
+    if header.chunk_size.is_some() {
+        return Err(StrategyCryptoError::new_err(
+            "该密文为分块流格式，请使用 DecryptReader 读取",
+        ));
+    }
+    let blob = sign::verify(header, blob)?;
+    revocation::check_header(header)?;
+    let Some(expected) = &header.commitment else {
+        return Err(MalformedCiphertextError::new_err("密文缺少密钥承诺"));
+    };
+    let (aad, rest) = blob.split_at(header_len);
+    let (nonce, ct) = rest.split_at(header.nonce_len());
+    let cipher = AnyCipher::new(header.alg, &verify_commitment(key, nonce, expected)?)?;
+    let mut pt = decrypt_in_place(&cipher, nonce, aad, ct)?;
+    if header.padding.is_some() {
+        pt = padding::unpad(pt)?;
+    }
+    match &header.compression {
+        Some(compression) => compress::decompress(compression, &pt),
+        None => Ok(pt),
+    }
+}
+
+/// 解密容器或旧版无头格式；旧版格式没有头部，返回 `None`
+pub fn open(key: &Key, blob: &[u8]) -> PyResult<(Option<Header>, SecretBytes)> {
+    if container::has_magic(blob) {
+        let (header, header_len) = Header::parse(blob)?;
+        if header.kdf.is_some() {
+            return Err(InvalidKeyError::new_err(
+                "该密文使用口令加密，请使用 decrypt_with_password",
+            ));
+        }
+        let pt = open_container(key, blob, &header, header_len)?;
+        Ok((Some(header), pt))
+    } else {
+        sign::check_unsigned_allowed()?;
+        if blob.len() < NONCE_LEN {
+            return Err(MalformedCiphertextError::new_err("密文格式错误，长度不足"));
+        }
+        let cipher = AnyCipher::new(Algorithm::Aes256Gcm, key)?;
+        let (nonce, ct) = blob.split_at(NONCE_LEN);
+        let pt = decrypt_in_place(&cipher, nonce, &[], ct)?;
+        Ok((None, pt))
+    }
+}
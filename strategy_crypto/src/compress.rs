@@ -0,0 +1,142 @@
+//! 加密前压缩
+//!
+//! 压缩算法和原始长度记录在头部扩展条目中并随头部一起认证，解密后自动解压。
+//! 解压前先按头部记录的原始长度检查上限，输出缓冲区按该长度一次分配且不会增长，
+//! 恶意服务端无法借压缩炸弹耗尽内存。
+//! 注意压缩会让密文长度随明文内容变化，不要把机密数据与攻击者可控的数据压缩在同一条密文中。
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use zstd::bulk::{Compressor, Decompressor};
+use zstd::zstd_safe::compress_bound;
+
+use crate::error::{MalformedCiphertextError, StrategyCryptoError, UnsupportedVersionError};
+use crate::secret::SecretBytes;
+
+/// 默认解压上限 256 MiB
+pub const DEFAULT_MAX_DECOMPRESSED_SIZE: u64 = 256 * 1024 * 1024;
+
+static MAX_DECOMPRESSED_SIZE: AtomicU64 = AtomicU64::new(DEFAULT_MAX_DECOMPRESSED_SIZE);
+
+/// codec(1) | original_len(8)
+const ENCODED_LEN: usize = 1 + 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Codec {
+    Zstd,
+}
+
+impl Codec {
+    pub fn id(self) -> u8 {
+        match self {
+            Codec::Zstd => 1,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Codec::Zstd => "zstd",
+        }
+    }
+
+    /// 解析 Python 传入的压缩算法名，`None` 表示不压缩
+    pub fn from_name(name: Option<&str>) -> PyResult<Option<Self>> {
+        match name {
+            None => Ok(None),
+            Some(name) if name.eq_ignore_ascii_case("zstd") => Ok(Some(Codec::Zstd)),
+            Some(name) => Err(PyValueError::new_err(format!(
+                "不支持的压缩算法: {name}，可选 zstd"
+            ))),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Compression {
+    pub codec: Codec,
+    /// 压缩前的明文长度；加密时由 `seal` 填写
+    pub original_len: u64,
+}
+
+impl Compression {
+    pub fn new(codec: Codec) -> Self {
+        Compression {
+            codec,
+            original_len: 0,
+        }
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(ENCODED_LEN);
+        out.push(self.codec.id());
+        out.extend_from_slice(&self.original_len.to_le_bytes());
+        out
+    }
+
+    pub fn decode(raw: &[u8]) -> PyResult<Self> {
+        if raw.len() != ENCODED_LEN {
+            return Err(MalformedCiphertextError::new_err("压缩参数长度错误"));
+        }
+        let codec = match raw[0] {
+            1 => Codec::Zstd,
+            other => {
+                return Err(UnsupportedVersionError::new_err(format!(
+                    "不支持的压缩算法: {other}"
+                )))
+            }
+        };
+        let mut len = [0u8; 8];
+        len.copy_from_slice(&raw[1..]);
+        Ok(Compression {
+            codec,
+            original_len: u64::from_le_bytes(len),
+        })
+    }
+}
+
+/// 压缩到安全缓冲区，压缩结果同样是敏感数据
+pub fn compress(codec: Codec, data: &[u8]) -> PyResult<SecretBytes> {
+    match codec {
+        Codec::Zstd => {
+            let mut out = SecretBytes::with_capacity(compress_bound(data.len()));
+            Compressor::new(zstd::DEFAULT_COMPRESSION_LEVEL)
+                .and_then(|mut c| c.compress_to_buffer(data, out.as_vec_mut()))
+                .map_err(|e| StrategyCryptoError::new_err(format!("压缩失败: {e}")))?;
+            Ok(out)
+        }
+    }
+}
+
+/// 解压并核对长度；头部记录的原始长度超过上限时直接拒绝，不做任何解压
+pub fn decompress(compression: &Compression, data: &[u8]) -> PyResult<SecretBytes> {
+    let limit = MAX_DECOMPRESSED_SIZE.load(Ordering::Relaxed);
+    if compression.original_len > limit {
+        return Err(MalformedCiphertextError::new_err(format!(
+            "解压后大小 {} 超过上限 {limit}",
+            compression.original_len
+        )));
+    }
+    let len = compression.original_len as usize;
+    let mut out = SecretBytes::with_capacity(len);
+    match compression.codec {
+        Codec::Zstd => {
+            Decompressor::new()
+                .and_then(|mut d| d.decompress_to_buffer(data, out.as_vec_mut()))
+                .map_err(|e| MalformedCiphertextError::new_err(format!("解压失败: {e}")))?;
+        }
+    }
+    if out.len() != len {
+        return Err(MalformedCiphertextError::new_err(
+            "解压后长度与头部记录不一致",
+        ));
+    }
+    Ok(out)
+}
+
+/// 设置解压后明文的大小上限（字节），返回原来的上限
+#[pyfunction]
+pub fn set_max_decompressed_size(limit: u64) -> u64 {
+    MAX_DECOMPRESSED_SIZE.swap(limit, Ordering::Relaxed)
+}
@@ -0,0 +1,664 @@
+//! 密文容器格式
+//!
+//! v1 布局（多字节整数均为小端）：
+//!
+//! ```text
+//! magic(4) "BTSC" | version(1) | alg(1) | flags(2) | key_id_len(1) | key_id
+//! | ext_len(2) | ext(TLV) | nonce | ciphertext||tag [| signature(64)]
+//! ```
+//!
+//! nonce 之前的全部字节作为 AEAD 的关联数据参与认证，篡改头部任何字段都会导致解密失败。
+//! 扩展区由若干 `tag(1) | len(2) | value` 条目组成，未知 tag 会被忽略；
+//! 改变解密语义的特性必须通过 flags 声明，未知 flags 一律拒绝。
+//! 容器格式必须带有密钥承诺（`FLAG_KEY_COMMITTED`），缺少承诺的容器一律拒绝，见 `cipher` 模块。
+//! 分块流格式（`FLAG_STREAM`）在头部之后不是单个 AEAD 消息，而是 `salt | chunk...`，见 `stream` 模块。
+//! 带发布者签名的密文（`FLAG_SIGNED`）在末尾附加覆盖此前全部字节的 Ed25519 签名，见 `sign` 模块；
+//! 分块流则在每块之后附加该块的签名。
+//! 不带 magic 的数据视为旧版无头格式：`nonce(12) || ciphertext || tag`。
+
+use std::collections::BTreeMap;
+use std::io::Read;
+
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+
+use crate::compress::Compression;
+use crate::error::{AuthenticationError, MalformedCiphertextError, UnsupportedVersionError};
+use crate::kdf::KdfParams;
+use crate::padding::Padding;
+use crate::sign;
+
+pub const MAGIC: &[u8; 4] = b"BTSC";
+pub const FORMAT_VERSION: u8 = 1;
+
+/// 加密算法，id 写入头部 alg 字段；旧版无头格式固定为 AES-256-GCM
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Algorithm {
+    #[default]
+    Aes256Gcm,
+    ChaCha20Poly1305,
+    XChaCha20Poly1305,
+    /// 抗 nonce 误用：nonce 重复时只会泄露“明文是否相同”
+    Aes256GcmSiv,
+}
+
+impl Algorithm {
+    pub const ALL: [Algorithm; 4] = [
+        Algorithm::Aes256Gcm,
+        Algorithm::ChaCha20Poly1305,
+        Algorithm::XChaCha20Poly1305,
+        Algorithm::Aes256GcmSiv,
+    ];
+
+    pub fn id(self) -> u8 {
+        match self {
+            Algorithm::Aes256Gcm => 1,
+            Algorithm::ChaCha20Poly1305 => 2,
+            Algorithm::XChaCha20Poly1305 => 3,
+            Algorithm::Aes256GcmSiv => 4,
+        }
+    }
+
+    pub fn from_id(id: u8) -> Option<Self> {
+        Algorithm::ALL.into_iter().find(|alg| alg.id() == id)
+    }
+
+    /// Python 侧使用的算法名
+    pub fn name(self) -> &'static str {
+        match self {
+            Algorithm::Aes256Gcm => "aes-256-gcm",
+            Algorithm::ChaCha20Poly1305 => "chacha20-poly1305",
+            Algorithm::XChaCha20Poly1305 => "xchacha20-poly1305",
+            Algorithm::Aes256GcmSiv => "aes-256-gcm-siv",
+        }
+    }
+
+    /// 解析 Python 传入的算法名（不区分大小写），`None` 表示默认的 AES-256-GCM
+    pub fn from_name(name: Option<&str>) -> PyResult<Self> {
+        let Some(name) = name else {
+            return Ok(Algorithm::default());
+        };
+        Algorithm::ALL
+            .into_iter()
+            .find(|alg| alg.name().eq_ignore_ascii_case(name))
+            .ok_or_else(|| {
+                let names: Vec<_> = Algorithm::ALL.iter().map(|alg| alg.name()).collect();
+                PyValueError::new_err(format!(
+                    "不支持的加密算法: {name}，可选 {}",
+                    names.join(" / ")
+                ))
+            })
+    }
+
+    pub fn nonce_len(self) -> usize {
+        match self {
+            Algorithm::XChaCha20Poly1305 => 24,
+            _ => NONCE_LEN,
+        }
+    }
+}
+
+/// 密钥由口令经 Argon2id 派生，派生参数见扩展条目 `EXT_KDF`
+pub const FLAG_PASSWORD: u16 = 0x0001;
+
+/// 载荷使用承诺派生的子密钥加密，承诺值见扩展条目 `EXT_COMMIT`，解密前先校验
+pub const FLAG_KEY_COMMITTED: u16 = 0x0002;
+
+/// 分块流格式，分块大小见扩展条目 `EXT_STREAM`
+pub const FLAG_STREAM: u16 = 0x0004;
+
+/// 明文加密前经过压缩，压缩参数见扩展条目 `EXT_COMPRESSION`
+pub const FLAG_COMPRESSED: u16 = 0x0008;
+
+/// 明文加密前按策略填充，填充策略见扩展条目 `EXT_PADDING`
+pub const FLAG_PADDED: u16 = 0x0010;
+
+/// 末尾附带发布者签名，签名公钥见扩展条目 `EXT_SIGNER`
+pub const FLAG_SIGNED: u16 = 0x0020;
+
+/// 当前版本已定义的 flags 位；出现未知位时拒绝解析
+pub const KNOWN_FLAGS: u16 =
+    FLAG_PASSWORD | FLAG_KEY_COMMITTED | FLAG_STREAM | FLAG_COMPRESSED | FLAG_PADDED | FLAG_SIGNED;
+
+/// 旧版无头格式及 96-bit nonce 算法的 nonce 长度
+pub const NONCE_LEN: usize = 12;
+pub const TAG_LEN: usize = 16;
+/// 密钥承诺值长度（HKDF-SHA256 输出）
+pub const COMMIT_LEN: usize = 32;
+/// 分块流格式紧跟头部的随机盐长度，用于派生加密子密钥与承诺值
+pub const STREAM_SALT_LEN: usize = 32;
+/// 分块明文大小的取值范围
+pub const MIN_CHUNK_SIZE: u32 = 1024;
+pub const MAX_CHUNK_SIZE: u32 = 16 * 1024 * 1024;
+/// Ed25519 公钥与签名长度
+pub const PUBLIC_KEY_LEN: usize = 32;
+pub const SIGNATURE_LEN: usize = 64;
+
+/// 扩展条目：关联数据上下文（策略 ID、版本、授权用户等）
+const EXT_CONTEXT: u8 = 0x01;
+/// 扩展条目：口令派生参数（算法、代价、盐）
+const EXT_KDF: u8 = 0x02;
+/// 扩展条目：密钥承诺值
+const EXT_COMMIT: u8 = 0x03;
+/// 扩展条目：分块流的分块明文大小（u32）
+const EXT_STREAM: u8 = 0x04;
+/// 扩展条目：压缩算法与原始长度
+const EXT_COMPRESSION: u8 = 0x05;
+/// 扩展条目：长度填充策略
+const EXT_PADDING: u8 = 0x06;
+/// 扩展条目：发布者 Ed25519 公钥
+const EXT_SIGNER: u8 = 0x07;
+
+/// 固定部分长度：magic + version + alg + flags + key_id_len
+const FIXED_LEN: usize = 4 + 1 + 1 + 2 + 1;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Header {
+    pub version: u8,
+    pub alg: Algorithm,
+    pub flags: u16,
+    pub key_id: String,
+    /// 绑定到密文的上下文，随头部一起参与认证
+    pub context: Option<Context>,
+    /// 口令派生参数；存在时 flags 必须包含 `FLAG_PASSWORD`
+    pub kdf: Option<KdfParams>,
+    /// 密钥承诺值；存在时 flags 必须包含 `FLAG_KEY_COMMITTED`
+    pub commitment: Option<[u8; COMMIT_LEN]>,
+    /// 分块流的分块明文大小；存在时 flags 必须包含 `FLAG_STREAM`
+    pub chunk_size: Option<u32>,
+    /// 压缩参数；存在时 flags 必须包含 `FLAG_COMPRESSED`
+    pub compression: Option<Compression>,
+    /// 填充策略；存在时 flags 必须包含 `FLAG_PADDED`
+    pub padding: Option<Padding>,
+    /// 发布者公钥；存在时 flags 必须包含 `FLAG_SIGNED`
+    pub signer: Option<[u8; PUBLIC_KEY_LEN]>,
+}
+
+/// 关联数据上下文，常用键为 `strategy_id`、`version`、`licensee`
+pub type Context = BTreeMap<String, String>;
+
+impl Header {
+    pub fn new(alg: Algorithm, key_id: Option<&str>) -> PyResult<Self> {
+        let key_id = key_id.unwrap_or_default();
+        if key_id.len() > u8::MAX as usize {
+            return Err(PyValueError::new_err("key_id 过长，最多 255 字节"));
+        }
+        Ok(Header {
+            version: FORMAT_VERSION,
+            alg,
+            flags: 0,
+            key_id: key_id.to_string(),
+            context: None,
+            kdf: None,
+            commitment: None,
+            chunk_size: None,
+            compression: None,
+            padding: None,
+            signer: None,
+        })
+    }
+
+    /// 编码时的 flags：由头部携带的可选特性推导
+    pub fn flags(&self) -> u16 {
+        let mut flags = self.flags;
+        if self.kdf.is_some() {
+            flags |= FLAG_PASSWORD;
+        }
+        if self.commitment.is_some() {
+            flags |= FLAG_KEY_COMMITTED;
+        }
+        if self.chunk_size.is_some() {
+            flags |= FLAG_STREAM;
+        }
+        if self.compression.is_some() {
+            flags |= FLAG_COMPRESSED;
+        }
+        if self.padding.is_some() {
+            flags |= FLAG_PADDED;
+        }
+        if self.signer.is_some() {
+            flags |= FLAG_SIGNED;
+        }
+        flags
+    }
+
+    pub fn nonce_len(&self) -> usize {
+        self.alg.nonce_len()
+    }
+
+    /// 分块流每块在明文之外的字节数：tag，带发布者签名时再加每块的签名
+    pub fn chunk_overhead(&self) -> usize {
+        if self.signer.is_some() {
+            TAG_LEN + SIGNATURE_LEN
+        } else {
+            TAG_LEN
+        }
+    }
+
+    /// 头部之后至少应有的字节数
+    fn min_body_len(&self) -> usize {
+        if self.chunk_size.is_some() {
+            STREAM_SALT_LEN + self.chunk_overhead()
+        } else if self.signer.is_some() {
+            self.nonce_len() + TAG_LEN + SIGNATURE_LEN
+        } else {
+            self.nonce_len() + TAG_LEN
+        }
+    }
+
+    /// 序列化头部；返回值同时用作 AEAD 关联数据
+    pub fn encode(&self) -> PyResult<Vec<u8>> {
+        let mut ext = Vec::new();
+        if let Some(context) = &self.context {
+            push_ext(&mut ext, EXT_CONTEXT, &encode_context(context)?)?;
+        }
+        if let Some(kdf) = &self.kdf {
+            push_ext(&mut ext, EXT_KDF, &kdf.encode())?;
+        }
+        if let Some(commitment) = &self.commitment {
+            push_ext(&mut ext, EXT_COMMIT, commitment)?;
+        }
+        if let Some(chunk_size) = self.chunk_size {
+            push_ext(&mut ext, EXT_STREAM, &chunk_size.to_le_bytes())?;
+        }
+        if let Some(compression) = &self.compression {
+            push_ext(&mut ext, EXT_COMPRESSION, &compression.encode())?;
+        }
+        if let Some(padding) = self.padding {
+            push_ext(&mut ext, EXT_PADDING, &padding.encode())?;
+        }
+        if let Some(signer) = &self.signer {
+            push_ext(&mut ext, EXT_SIGNER, signer)?;
+        }
+        if ext.len() > u16::MAX as usize {
+            return Err(PyValueError::new_err("头部扩展字段过长"));
+        }
+        let mut out = Vec::with_capacity(FIXED_LEN + self.key_id.len() + 2 + ext.len());
+        out.extend_from_slice(MAGIC);
+        out.push(self.version);
+        out.push(self.alg.id());
+        out.extend_from_slice(&self.flags().to_le_bytes());
+        out.push(self.key_id.len() as u8);
+        out.extend_from_slice(self.key_id.as_bytes());
+        out.extend_from_slice(&(ext.len() as u16).to_le_bytes());
+        out.extend_from_slice(&ext);
+        Ok(out)
+    }
+
+    /// 解析头部，返回 (头部, 头部字节长度)
+    pub fn parse(blob: &[u8]) -> PyResult<(Self, usize)> {
+        let (header, pos) = Header::parse_prefix(blob)?;
+        if blob.len() < pos + header.min_body_len() {
+            return Err(MalformedCiphertextError::new_err("密文格式错误，长度不足"));
+        }
+        Ok((header, pos))
+    }
+
+    /// 从流中读出并解析头部，返回 (头部, 头部原始字节)；读取位置停在头部之后
+    pub fn read_from(reader: &mut impl Read) -> PyResult<(Self, Vec<u8>)> {
+        let mut raw = vec![0u8; FIXED_LEN];
+        read_header_bytes(reader, &mut raw)?;
+        if !has_magic(&raw) {
+            return Err(MalformedCiphertextError::new_err("缺少密文容器标识"));
+        }
+        let key_id_len = raw[FIXED_LEN - 1] as usize;
+        raw.resize(FIXED_LEN + key_id_len + 2, 0);
+        read_header_bytes(reader, &mut raw[FIXED_LEN..])?;
+        let ext_len = u16::from_le_bytes([raw[raw.len() - 2], raw[raw.len() - 1]]) as usize;
+        let start = raw.len();
+        raw.resize(start + ext_len, 0);
+        read_header_bytes(reader, &mut raw[start..])?;
+        let (header, _) = Header::parse_prefix(&raw)?;
+        Ok((header, raw))
+    }
+
+    /// 只解析头部本身，不检查之后的载荷长度
+    fn parse_prefix(blob: &[u8]) -> PyResult<(Self, usize)> {
+        if !has_magic(blob) {
+            return Err(MalformedCiphertextError::new_err("缺少密文容器标识"));
+        }
+        if blob.len() < FIXED_LEN {
+            return Err(MalformedCiphertextError::new_err("密文头部长度不足"));
+        }
+        let version = blob[4];
+        if version != FORMAT_VERSION {
+            return Err(UnsupportedVersionError::new_err(format!(
+                "不支持的密文格式版本: {version}"
+            )));
+        }
+        let alg = Algorithm::from_id(blob[5]).ok_or_else(|| {
+            UnsupportedVersionError::new_err(format!("不支持的加密算法: {}", blob[5]))
+        })?;
+        let flags = u16::from_le_bytes([blob[6], blob[7]]);
+        if flags & !KNOWN_FLAGS != 0 {
+            return Err(UnsupportedVersionError::new_err(format!(
+                "密文包含未知 flags: {flags:#06x}"
+            )));
+        }
+        let mut reader = header_reader(blob);
+        reader.take(FIXED_LEN)?;
+        let key_id_len = blob[FIXED_LEN - 1] as usize;
+        let key_id = std::str::from_utf8(reader.take(key_id_len)?)
+            .map_err(|_| MalformedCiphertextError::new_err("key_id 不是合法的 UTF-8"))?
+            .to_string();
+        let ext_len = u16::from_le_bytes(reader.array()?) as usize;
+        let ext = reader.take(ext_len)?;
+        let mut header = Header {
+            version,
+            alg,
+            flags,
+            key_id,
+            context: None,
+            kdf: None,
+            commitment: None,
+            chunk_size: None,
+            compression: None,
+            padding: None,
+            signer: None,
+        };
+        let mut ext = header_reader(ext);
+        while !ext.is_empty() {
+            let [tag] = ext.array()?;
+            let len = u16::from_le_bytes(ext.array()?) as usize;
+            let value = ext.take(len)?;
+            match tag {
+                EXT_CONTEXT => header.context = Some(decode_context(value)?),
+                EXT_KDF => header.kdf = Some(KdfParams::decode(value)?),
+                EXT_COMMIT => {
+                    let commitment = value
+                        .try_into()
+                        .map_err(|_| MalformedCiphertextError::new_err("密钥承诺值长度错误"))?;
+                    header.commitment = Some(commitment);
+                }
+                EXT_STREAM => {
+                    let chunk_size = value
+                        .try_into()
+                        .map(u32::from_le_bytes)
+                        .map_err(|_| MalformedCiphertextError::new_err("分块大小字段长度错误"))?;
+                    if !(MIN_CHUNK_SIZE..=MAX_CHUNK_SIZE).contains(&chunk_size) {
+                        return Err(MalformedCiphertextError::new_err(format!(
+                            "分块大小超出范围: {chunk_size}"
+                        )));
+                    }
+                    header.chunk_size = Some(chunk_size);
+                }
+                EXT_COMPRESSION => header.compression = Some(Compression::decode(value)?),
+                EXT_PADDING => header.padding = Some(Padding::decode(value)?),
+                EXT_SIGNER => {
+                    let signer = value
+                        .try_into()
+                        .map_err(|_| MalformedCiphertextError::new_err("发布者公钥长度错误"))?;
+                    header.signer = Some(signer);
+                }
+                _ => {}
+            }
+        }
+        if (flags & FLAG_PASSWORD != 0) != header.kdf.is_some() {
+            return Err(MalformedCiphertextError::new_err(
+                "口令加密标志与派生参数不一致",
+            ));
+        }
+        if (flags & FLAG_KEY_COMMITTED != 0) != header.commitment.is_some() {
+            return Err(MalformedCiphertextError::new_err(
+                "密钥承诺标志与承诺值不一致",
+            ));
+        }
+        if (flags & FLAG_STREAM != 0) != header.chunk_size.is_some() {
+            return Err(MalformedCiphertextError::new_err(
+                "分块流标志与分块大小不一致",
+            ));
+        }
+        if (flags & FLAG_COMPRESSED != 0) != header.compression.is_some() {
+            return Err(MalformedCiphertextError::new_err(
+                "压缩标志与压缩参数不一致",
+            ));
+        }
+        if (flags & FLAG_PADDED != 0) != header.padding.is_some() {
+            return Err(MalformedCiphertextError::new_err(
+                "填充标志与填充策略不一致",
+            ));
+        }
+        if (flags & FLAG_SIGNED != 0) != header.signer.is_some() {
+            return Err(MalformedCiphertextError::new_err(
+                "签名标志与发布者公钥不一致",
+            ));
+        }
+        Ok((header, reader.pos()))
+    }
+}
+
+pub fn has_magic(blob: &[u8]) -> bool {
+    blob.starts_with(MAGIC)
+}
+
+/// 无需密钥即可判断数据是否为容器格式密文（只检查 magic，不解析头部）。
+///
+/// 旧版无头密文无法与任意二进制数据区分，这里一律返回 False。
+#[pyfunction]
+pub fn is_encrypted(blob: &[u8]) -> bool {
+    has_magic(blob) && blob.len() >= FIXED_LEN
+}
+
+/// 无需密钥解析容器头部，返回格式版本、算法、key_id、载荷长度以及公开的关联数据。
+///
+/// 头部只有在解密成功后才算经过认证，这里的结果仅供展示和选择加载路径。
+#[pyfunction]
+pub fn inspect<'py>(py: Python<'py>, blob: &[u8]) -> PyResult<Bound<'py, PyDict>> {
+    let (header, header_len) = Header::parse(blob)?;
+    let payload_len = match header.chunk_size {
+        Some(chunk_size) => {
+            let body = blob.len() - header_len - STREAM_SALT_LEN;
+            stream_layout(body as u64, chunk_size, header.chunk_overhead()).1 as usize
+        }
+        None => {
+            let signature_len = if header.signer.is_some() {
+                SIGNATURE_LEN
+            } else {
+                0
+            };
+            blob.len() - header_len - header.nonce_len() - TAG_LEN - signature_len
+        }
+    };
+    let info = PyDict::new_bound(py);
+    info.set_item("format_version", header.version)?;
+    info.set_item("algorithm", header.alg.name())?;
+    info.set_item("algorithm_id", header.alg.id())?;
+    info.set_item("flags", header.flags)?;
+    if let Some(kdf) = &header.kdf {
+        let params = PyDict::new_bound(py);
+        params.set_item("algorithm", "Argon2id")?;
+        params.set_item("m_cost", kdf.m_cost)?;
+        params.set_item("t_cost", kdf.t_cost)?;
+        params.set_item("p_cost", kdf.p_cost)?;
+        info.set_item("kdf", params)?;
+    } else {
+        info.set_item("kdf", py.None())?;
+    }
+    info.set_item(
+        "key_id",
+        (!header.key_id.is_empty()).then_some(header.key_id.as_str()),
+    )?;
+    info.set_item("key_committed", header.commitment.is_some())?;
+    info.set_item("chunk_size", header.chunk_size)?;
+    if let Some(compression) = &header.compression {
+        let params = PyDict::new_bound(py);
+        params.set_item("codec", compression.codec.name())?;
+        params.set_item("original_len", compression.original_len)?;
+        info.set_item("compression", params)?;
+    } else {
+        info.set_item("compression", py.None())?;
+    }
+    info.set_item("padding", header.padding.map(Padding::spec))?;
+    info.set_item(
+        "signer",
+        header.signer.as_ref().map(sign::encode_public_key),
+    )?;
+    info.set_item("header_len", header_len)?;
+    info.set_item("payload_len", payload_len)?;
+    info.set_item("total_len", blob.len())?;
+    info.set_item("context", &header.context)?;
+    Ok(info)
+}
+
+/// 由分块流 salt 之后的密文长度计算 (分块数, 明文长度)。
+///
+/// 分块大小固定，第 i 块位于 salt 之后 `i * (chunk_size + overhead)` 处，按偏移即可直接定位；
+/// `overhead` 见 `Header::chunk_overhead`
+pub fn stream_layout(body_len: u64, chunk_size: u32, overhead: usize) -> (u64, u64) {
+    let overhead = overhead as u64;
+    let chunks = body_len.div_ceil(chunk_size as u64 + overhead);
+    (chunks, body_len.saturating_sub(chunks * overhead))
+}
+
+fn read_header_bytes(reader: &mut impl Read, buf: &mut [u8]) -> PyResult<()> {
+    reader.read_exact(buf).map_err(|e| match e.kind() {
+        std::io::ErrorKind::UnexpectedEof => MalformedCiphertextError::new_err("密文头部被截断"),
+        _ => crate::stream::io_err(e),
+    })
+}
+
+/// 二进制字段的读取游标，供容器头部、策略包清单与签名凭证共用。
+///
+/// 字符串为 `len(2) | UTF-8`；`what` 与 `err` 决定越界或编码错误时的提示与异常类型
+pub struct Reader<'a> {
+    raw: &'a [u8],
+    pos: usize,
+    what: &'static str,
+    err: fn(String) -> PyErr,
+}
+
+impl<'a> Reader<'a> {
+    pub fn new(raw: &'a [u8], what: &'static str, err: fn(String) -> PyErr) -> Self {
+        Reader {
+            raw,
+            pos: 0,
+            what,
+            err,
+        }
+    }
+
+    pub fn pos(&self) -> usize {
+        self.pos
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pos == self.raw.len()
+    }
+
+    pub fn take(&mut self, n: usize) -> PyResult<&'a [u8]> {
+        let end = self
+            .pos
+            .checked_add(n)
+            .filter(|&end| end <= self.raw.len())
+            .ok_or_else(|| (self.err)(format!("{}被截断", self.what)))?;
+        let out = &self.raw[self.pos..end];
+        self.pos = end;
+        Ok(out)
+    }
+
+    pub fn array<const N: usize>(&mut self) -> PyResult<[u8; N]> {
+        let mut out = [0u8; N];
+        out.copy_from_slice(self.take(N)?);
+        Ok(out)
+    }
+
+    pub fn string(&mut self) -> PyResult<String> {
+        let len = u16::from_le_bytes(self.array()?) as usize;
+        std::str::from_utf8(self.take(len)?)
+            .map(str::to_string)
+            .map_err(|_| (self.err)(format!("{}不是合法的 UTF-8", self.what)))
+    }
+}
+
+fn header_reader(raw: &[u8]) -> Reader<'_> {
+    Reader::new(raw, "密文头部", MalformedCiphertextError::new_err)
+}
+
+fn push_ext(ext: &mut Vec<u8>, tag: u8, value: &[u8]) -> PyResult<()> {
+    if value.len() > u16::MAX as usize {
+        return Err(PyValueError::new_err("头部扩展条目过长"));
+    }
+    ext.push(tag);
+    ext.extend_from_slice(&(value.len() as u16).to_le_bytes());
+    ext.extend_from_slice(value);
+    Ok(())
+}
+
+/// 编码：count(1) 后接若干 `key_len(1) | key | value_len(2) | value`，按键排序
+pub fn encode_context(context: &Context) -> PyResult<Vec<u8>> {
+    if context.len() > u8::MAX as usize {
+        return Err(PyValueError::new_err("关联数据条目过多，最多 255 项"));
+    }
+    let mut out = vec![context.len() as u8];
+    for (k, v) in context {
+        if k.is_empty() || k.len() > u8::MAX as usize {
+            return Err(PyValueError::new_err(format!(
+                "关联数据键长度需为 1..=255 字节: {k:?}"
+            )));
+        }
+        if v.len() > u16::MAX as usize {
+            return Err(PyValueError::new_err(format!("关联数据值过长: {k}")));
+        }
+        out.push(k.len() as u8);
+        out.extend_from_slice(k.as_bytes());
+        out.extend_from_slice(&(v.len() as u16).to_le_bytes());
+        out.extend_from_slice(v.as_bytes());
+    }
+    Ok(out)
+}
+
+fn decode_context(raw: &[u8]) -> PyResult<Context> {
+    let mut reader = header_reader(raw);
+    let [count] = reader.array()?;
+    let mut context = Context::new();
+    for _ in 0..count {
+        let [k_len] = reader.array()?;
+        let k = reader.take(k_len as usize)?;
+        let v_len = u16::from_le_bytes(reader.array()?) as usize;
+        let v = reader.take(v_len)?;
+        let (Ok(k), Ok(v)) = (std::str::from_utf8(k), std::str::from_utf8(v)) else {
+            return Err(MalformedCiphertextError::new_err(
+                "关联数据不是合法的 UTF-8",
+            ));
+        };
+        context.insert(k.to_string(), v.to_string());
+    }
+    Ok(context)
+}
+
+/// 校验密文绑定的上下文：`expected` 中的每一项都必须与密文记录一致。
+///
+/// `allow_unbound` 只放行没有头部的旧版密文；容器格式与本模块同时引入，
+/// 未绑定上下文的容器一律拒绝，防止新造的无上下文密文被当作任意策略加载。
+pub fn check_context(
+    header: Option<&Header>,
+    expected: &Context,
+    allow_unbound: bool,
+) -> PyResult<()> {
+    let Some(header) = header else {
+        if allow_unbound {
+            return Ok(());
+        }
+        return Err(AuthenticationError::new_err("密文未绑定关联数据"));
+    };
+    let Some(stored) = &header.context else {
+        return Err(AuthenticationError::new_err("密文未绑定关联数据"));
+    };
+    for (k, v) in expected {
+        match stored.get(k) {
+            Some(actual) if actual == v => {}
+            Some(actual) => {
+                return Err(AuthenticationError::new_err(format!(
+                    "关联数据不匹配: {k} 期望 {v:?}，密文为 {actual:?}"
+                )))
+            }
+            None => {
+                return Err(AuthenticationError::new_err(format!(
+                    "密文缺少关联数据项: {k}"
+                )));
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,69 @@
+//! 暴露给 Python 的异常类型
+//!
+//! 所有异常均派生自 `StrategyCryptoError`（其本身是 `ValueError` 的子类，兼容旧的调用方），
+//! 调用方可以据此区分“不是密文”“密钥错误”“密文被篡改”等情况。
+
+use pyo3::create_exception;
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+
+create_exception!(strategy_crypto, StrategyCryptoError, PyValueError);
+// 密钥无法解析或长度不对
+create_exception!(strategy_crypto, InvalidKeyError, StrategyCryptoError);
+// 未配置密钥（既没有传入 key，也没有设置 STRATEGY_KEY）
+create_exception!(strategy_crypto, KeyNotConfiguredError, InvalidKeyError);
+// AEAD 校验失败：密钥错误、密文被篡改或与期望的关联数据不符
+create_exception!(strategy_crypto, AuthenticationError, StrategyCryptoError);
+// 发布者签名缺失、无效或签名者不在受信任列表中
+create_exception!(strategy_crypto, SignatureError, AuthenticationError);
+// 数据不是合法的密文（长度不足、头部被截断等）
+create_exception!(
+    strategy_crypto,
+    MalformedCiphertextError,
+    StrategyCryptoError
+);
+// 容器版本、算法或 flags 不被当前扩展支持
+create_exception!(
+    strategy_crypto,
+    UnsupportedVersionError,
+    StrategyCryptoError
+);
+// 授权校验失败
+create_exception!(strategy_crypto, LicenseError, StrategyCryptoError);
+// 检测到系统时钟回拨超出容差
+create_exception!(strategy_crypto, ClockRollbackError, LicenseError);
+// 吊销列表无效或回退，或密钥、策略、授权已被吊销
+create_exception!(strategy_crypto, RevocationError, LicenseError);
+
+pub fn register(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    let py = m.py();
+    m.add(
+        "StrategyCryptoError",
+        py.get_type_bound::<StrategyCryptoError>(),
+    )?;
+    m.add("InvalidKeyError", py.get_type_bound::<InvalidKeyError>())?;
+    m.add(
+        "KeyNotConfiguredError",
+        py.get_type_bound::<KeyNotConfiguredError>(),
+    )?;
+    m.add(
+        "AuthenticationError",
+        py.get_type_bound::<AuthenticationError>(),
+    )?;
+    m.add("SignatureError", py.get_type_bound::<SignatureError>())?;
+    m.add(
+        "MalformedCiphertextError",
+        py.get_type_bound::<MalformedCiphertextError>(),
+    )?;
+    m.add(
+        "UnsupportedVersionError",
+        py.get_type_bound::<UnsupportedVersionError>(),
+    )?;
+    m.add("LicenseError", py.get_type_bound::<LicenseError>())?;
+    m.add(
+        "ClockRollbackError",
+        py.get_type_bound::<ClockRollbackError>(),
+    )?;
+    m.add("RevocationError", py.get_type_bound::<RevocationError>())?;
+    Ok(())
+}
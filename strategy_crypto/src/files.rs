@@ -0,0 +1,54 @@
+//! 文件系统辅助：目录遍历与原子写入
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use pyo3::prelude::*;
+
+/// `write_atomic` 使用的临时文件后缀，遍历目录时应跳过
+pub const TMP_SUFFIX: &str = ".tmp";
+
+/// 原子地写入文件：先写同目录临时文件再重命名，中途失败不会留下写了一半的文件。
+/// 父目录不存在时先创建
+pub fn write_atomic(path: &Path, data: &[u8]) -> std::io::Result<()> {
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        fs::create_dir_all(dir)?;
+    }
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(TMP_SUFFIX);
+    let tmp = PathBuf::from(tmp);
+    fs::write(&tmp, data)?;
+    fs::rename(&tmp, path).inspect_err(|_| {
+        let _ = fs::remove_file(&tmp);
+    })
+}
+
+/// 收集目录下的文件，`recursive` 为真时进入子目录；`skip` 按名称排除文件和目录。
+///
+/// file_type 不跟随符号链接：指向目录的链接直接跳过，避免链接成环时无限递归
+pub fn collect_files(
+    dir: &Path,
+    recursive: bool,
+    skip: &dyn Fn(&str) -> bool,
+    out: &mut Vec<PathBuf>,
+) -> PyResult<()> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        if skip(&entry.file_name().to_string_lossy()) {
+            continue;
+        }
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            if recursive {
+                collect_files(&path, recursive, skip, out)?;
+            }
+            continue;
+        }
+        if file_type.is_symlink() && path.is_dir() {
+            continue;
+        }
+        out.push(path);
+    }
+    Ok(())
+}
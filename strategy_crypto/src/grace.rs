@@ -0,0 +1,390 @@
+//! 离线宽限期
+//!
+//! 客户端偶尔连不上策略服务器，但授权的过期检查只依赖系统时钟，把时钟往回拨就能绕过。
+//! `OfflineGrace` 在本地状态文件中记录“最近一次联网确认的时间”和“见过的最晚时间”：
+//! 距上次联网超过宽限期即拒绝运行；当前时间比见过的最晚时间早出容差以上视为时钟回拨。
+//! 授权检查使用两者中较晚的时间，回拨时钟也无法让已过期的授权重新生效。
+//!
+//! “联网确认”只采信服务端签发给本机的时间凭证，本机时钟不参与：否则回拨时钟后再“联网确认”
+//! 一次就能把状态整体重置。凭证为文本 `btt1.<payload>.<signature>`，与授权凭证使用同一组授权公钥，
+//! payload 为 `version(1) | issued_at(8) | nonce(16) | machine`，machine 为 `machine_id()` 的结果。
+//!
+//! nonce 是客户端请求凭证前用 `time_challenge()` 生成的一次性挑战，只保存在本进程内存中，
+//! 记录联网时消耗掉：旧凭证无法重放（删除状态文件也不行），因此校验通过的凭证时间是新鲜的，
+//! 直接覆盖状态中的两个时间——曾被调快的本机时钟不会把授权锁死。
+//!
+//! 状态文件用 AES-256-GCM 认证加密，密钥由首次写入时随机生成的安装密钥（状态文件旁的 `.key`
+//! 文件，Unix 上权限为 0600）与本机机器标识经 HKDF 派生，布局：
+//!
+//! ```text
+//! magic "BTGS" | version(1) | nonce(12) | ct(last_online(8) | last_seen(8)) || tag(16)
+//! ```
+//!
+//! 这只能防止在不读取安装密钥的情况下改写状态、以及把状态文件拷贝到其他机器：能读取安装密钥的
+//! 本机用户仍可伪造状态，离线期间的时间判断终究依赖本机。删除状态文件或安装密钥只会回到
+//! “从未联网”，需要重新用服务端时间凭证确认联网。
+
+use std::fs::{self, OpenOptions};
+use std::io::{ErrorKind, Write};
+#[cfg(unix)]
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use aes_gcm::aead::generic_array::GenericArray;
+use aes_gcm::aead::{Aead, KeyInit, Payload};
+use aes_gcm::Aes256Gcm;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use hkdf::Hkdf;
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use rand::rngs::OsRng;
+use rand::RngCore;
+use sha2::Sha256;
+use zeroize::Zeroizing;
+
+use crate::container::NONCE_LEN;
+use crate::error::{ClockRollbackError, LicenseError};
+use crate::files;
+use crate::license::{
+    machine_id, now, open_token, push_str, raw_machine_id, sign_token, token_reader,
+};
+use crate::secret::SecretKey;
+use crate::sign::parse_signing_key;
+
+const MAGIC: &[u8; 4] = b"BTGS";
+const STATE_VERSION: u8 = 2;
+const PREFIX_LEN: usize = MAGIC.len() + 1;
+const STATE_LEN: usize = 16;
+const TAG_LEN: usize = 16;
+
+/// 状态文件密钥的派生标签
+const STATE_KEY_LABEL: &[u8] = b"bullet-trade/offline-grace/v1";
+
+const TIME_TOKEN_PREFIX: &str = "btt1.";
+const TIME_TOKEN_VERSION: u8 = 2;
+/// 时间凭证签名消息的域分隔前缀
+const TIME_TOKEN_LABEL: &[u8] = b"bullet-trade/server-time/v1";
+const TIME_NONCE_LEN: usize = 16;
+/// 同时等待响应的挑战数上限，超出时丢弃最早的挑战
+const MAX_PENDING_CHALLENGES: usize = 16;
+
+/// 默认宽限期 7 天
+const DEFAULT_GRACE_SECONDS: i64 = 7 * 24 * 3600;
+/// 默认允许的时钟回拨容差 5 分钟（NTP 校时等）
+const DEFAULT_ROLLBACK_TOLERANCE: i64 = 300;
+
+/// 本进程发起、尚未使用的时间凭证挑战
+static PENDING_CHALLENGES: Mutex<Vec<[u8; TIME_NONCE_LEN]>> = Mutex::new(Vec::new());
+
+#[derive(Debug, Clone, Copy)]
+struct State {
+    last_online: i64,
+    last_seen: i64,
+}
+
+/// 读取安装密钥；`create` 时不存在则随机生成，仅当前用户可读
+fn install_secret(path: &Path, create: bool) -> PyResult<SecretKey> {
+    let mut secret = SecretKey::zeroed();
+    match fs::read(path) {
+        Ok(raw) => {
+            let raw = Zeroizing::new(raw);
+            if raw.len() != secret.len() {
+                return Err(corrupted());
+            }
+            secret.copy_from_slice(&raw);
+            return Ok(secret);
+        }
+        Err(e) if e.kind() == ErrorKind::NotFound && create => {}
+        Err(e) if e.kind() == ErrorKind::NotFound => return Err(corrupted()),
+        Err(e) => return Err(e.into()),
+    }
+    OsRng.fill_bytes(&mut secret[..]);
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    options.mode(0o600);
+    match options.open(path) {
+        Ok(mut file) => file.write_all(&secret[..])?,
+        // 其他进程抢先生成时使用已有的安装密钥
+        Err(e) if e.kind() == ErrorKind::AlreadyExists => return install_secret(path, false),
+        Err(e) => return Err(e.into()),
+    }
+    Ok(secret)
+}
+
+/// 状态文件密钥：安装密钥为输入、机器标识为 info 经 HKDF 派生
+fn state_cipher(key_path: &Path, create: bool) -> PyResult<Aes256Gcm> {
+    let secret = install_secret(key_path, create)?;
+    let machine = raw_machine_id()?;
+    let hk = Hkdf::<Sha256>::new(Some(STATE_KEY_LABEL), &secret[..]);
+    let mut key = SecretKey::zeroed();
+    hk.expand(machine.as_bytes(), &mut key[..])
+        .map_err(|e| LicenseError::new_err(format!("HKDF 派生失败: {e}")))?;
+    Aes256Gcm::new_from_slice(&key[..]).map_err(|_| LicenseError::new_err("无法初始化离线状态密钥"))
+}
+
+fn parse_nonce(nonce: &str) -> PyResult<[u8; TIME_NONCE_LEN]> {
+    URL_SAFE_NO_PAD
+        .decode(nonce)
+        .ok()
+        .and_then(|raw| raw.try_into().ok())
+        .ok_or_else(|| PyValueError::new_err("时间凭证挑战格式错误"))
+}
+
+/// 消耗本进程发起的挑战；不存在说明凭证被重放或挑战已被使用
+fn take_challenge(nonce: &[u8; TIME_NONCE_LEN]) -> PyResult<()> {
+    let mut pending = PENDING_CHALLENGES.lock().unwrap_or_else(|e| e.into_inner());
+    let index = pending
+        .iter()
+        .position(|challenge| challenge == nonce)
+        .ok_or_else(|| {
+            LicenseError::new_err("时间凭证不是对本进程挑战的响应：凭证被重放或已使用")
+        })?;
+    pending.swap_remove(index);
+    Ok(())
+}
+
+/// 校验服务端签发的时间凭证并返回其中的服务端时间；凭证必须签发给本机并响应本进程的挑战
+fn verify_time_token(token: &str) -> PyResult<i64> {
+    let payload = open_token(token, TIME_TOKEN_PREFIX, TIME_TOKEN_LABEL)?;
+    let mut reader = token_reader(&payload);
+    let [version] = reader.array()?;
+    if version != TIME_TOKEN_VERSION {
+        return Err(LicenseError::new_err(format!(
+            "不支持的时间凭证版本: {version}"
+        )));
+    }
+    let issued_at = i64::from_le_bytes(reader.array()?);
+    let nonce = reader.array()?;
+    if reader.string()? != machine_id()? {
+        return Err(LicenseError::new_err("时间凭证不是签发给本机的"));
+    }
+    take_challenge(&nonce)?;
+    Ok(issued_at)
+}
+
+fn corrupted() -> PyErr {
+    LicenseError::new_err("离线状态文件校验失败：文件被篡改、安装密钥缺失或来自其他机器")
+}
+
+impl State {
+    fn seal(&self, cipher: &Aes256Gcm) -> PyResult<Vec<u8>> {
+        let mut plaintext = [0u8; STATE_LEN];
+        plaintext[..8].copy_from_slice(&self.last_online.to_le_bytes());
+        plaintext[8..].copy_from_slice(&self.last_seen.to_le_bytes());
+        let mut nonce = [0u8; NONCE_LEN];
+        OsRng.fill_bytes(&mut nonce);
+        let mut out = Vec::with_capacity(PREFIX_LEN + NONCE_LEN + STATE_LEN + TAG_LEN);
+        out.extend_from_slice(MAGIC);
+        out.push(STATE_VERSION);
+        let ct = cipher
+            .encrypt(
+                GenericArray::from_slice(&nonce),
+                Payload {
+                    msg: &plaintext,
+                    aad: &out,
+                },
+            )
+            .map_err(|_| LicenseError::new_err("离线状态加密失败"))?;
+        out.extend_from_slice(&nonce);
+        out.extend_from_slice(&ct);
+        Ok(out)
+    }
+
+    fn open(raw: &[u8], cipher: &Aes256Gcm) -> PyResult<Self> {
+        if raw.len() != PREFIX_LEN + NONCE_LEN + STATE_LEN + TAG_LEN || &raw[..4] != MAGIC {
+            return Err(corrupted());
+        }
+        if raw[4] != STATE_VERSION {
+            return Err(LicenseError::new_err(format!(
+                "不支持的离线状态版本: {}",
+                raw[4]
+            )));
+        }
+        let (aad, rest) = raw.split_at(PREFIX_LEN);
+        let (nonce, ct) = rest.split_at(NONCE_LEN);
+        let pt = cipher
+            .decrypt(GenericArray::from_slice(nonce), Payload { msg: ct, aad })
+            .map_err(|_| corrupted())?;
+        let mut last_online = [0u8; 8];
+        let mut last_seen = [0u8; 8];
+        last_online.copy_from_slice(&pt[..8]);
+        last_seen.copy_from_slice(&pt[8..]);
+        Ok(State {
+            last_online: i64::from_le_bytes(last_online),
+            last_seen: i64::from_le_bytes(last_seen),
+        })
+    }
+}
+
+/// 本地持久化的离线宽限期状态
+#[pyclass(module = "strategy_crypto")]
+pub struct OfflineGrace {
+    path: PathBuf,
+    grace_seconds: i64,
+    rollback_tolerance: i64,
+}
+
+impl OfflineGrace {
+    /// 安装密钥文件：状态文件路径加 `.key`
+    fn key_path(&self) -> PathBuf {
+        let mut path = self.path.as_os_str().to_owned();
+        path.push(".key");
+        PathBuf::from(path)
+    }
+
+    fn load(&self) -> PyResult<Option<State>> {
+        match fs::read(&self.path) {
+            Ok(raw) => State::open(&raw, &state_cipher(&self.key_path(), false)?).map(Some),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn store(&self, state: &State) -> PyResult<()> {
+        let cipher = state_cipher(&self.key_path(), true)?;
+        files::write_atomic(&self.path, &state.seal(&cipher)?)?;
+        Ok(())
+    }
+
+    /// 检查宽限期与时钟回拨并推进“见过的最晚时间”，返回用于授权检查的可信时间与剩余秒数
+    pub fn enforce(&self, now: i64) -> PyResult<(i64, i64)> {
+        let mut state = self
+            .load()?
+            .ok_or_else(|| LicenseError::new_err("尚未联网确认过授权，无法离线运行"))?;
+        if now < state.last_seen - self.rollback_tolerance {
+            return Err(ClockRollbackError::new_err(format!(
+                "检测到系统时钟回拨 {} 秒，请校准时间后联网确认授权",
+                state.last_seen - now
+            )));
+        }
+        let trusted_now = now.max(state.last_seen);
+        let remaining = self.grace_seconds - (trusted_now - state.last_online);
+        if remaining < 0 {
+            return Err(LicenseError::new_err("离线宽限期已用尽，请联网确认授权"));
+        }
+        if trusted_now > state.last_seen {
+            state.last_seen = trusted_now;
+            self.store(&state)?;
+        }
+        Ok((trusted_now, remaining))
+    }
+}
+
+#[pymethods]
+impl OfflineGrace {
+    /// `grace_seconds` 为距上次联网允许离线运行的时长，`rollback_tolerance` 为容忍的时钟回拨秒数
+    #[new]
+    #[pyo3(signature = (path, *, grace_seconds = DEFAULT_GRACE_SECONDS, rollback_tolerance = DEFAULT_ROLLBACK_TOLERANCE))]
+    fn new(path: PathBuf, grace_seconds: i64, rollback_tolerance: i64) -> PyResult<Self> {
+        if grace_seconds < 0 || rollback_tolerance < 0 {
+            return Err(PyValueError::new_err("宽限期与回拨容差不能为负数"));
+        }
+        Ok(OfflineGrace {
+            path,
+            grace_seconds,
+            rollback_tolerance,
+        })
+    }
+
+    #[getter]
+    fn path(&self) -> &Path {
+        &self.path
+    }
+
+    #[getter]
+    fn grace_seconds(&self) -> i64 {
+        self.grace_seconds
+    }
+
+    #[getter]
+    fn rollback_tolerance(&self) -> i64 {
+        self.rollback_tolerance
+    }
+
+    /// 最近一次联网确认的时间（Unix 秒），从未联网时为 None
+    #[getter]
+    fn last_online(&self) -> PyResult<Option<i64>> {
+        Ok(self.load()?.map(|state| state.last_online))
+    }
+
+    /// 见过的最晚时间（Unix 秒），从未联网时为 None
+    #[getter]
+    fn last_seen(&self) -> PyResult<Option<i64>> {
+        Ok(self.load()?.map(|state| state.last_seen))
+    }
+
+    /// 联网确认授权后调用，用服务端签发给本机的时间凭证（见 `issue_time_token`）重新开始计算宽限期。
+    ///
+    /// 凭证必须响应本进程 `time_challenge()` 生成的挑战，每个挑战只能使用一次；
+    /// 凭证时间直接覆盖原有状态（包括损坏的状态文件），本机时钟曾被调快时也由此恢复
+    fn record_online(&self, token: &str) -> PyResult<()> {
+        let server_now = verify_time_token(token)?;
+        self.store(&State {
+            last_online: server_now,
+            last_seen: server_now,
+        })
+    }
+
+    /// 检查宽限期与时钟回拨，返回剩余秒数；不满足时抛出 LicenseError / ClockRollbackError
+    #[pyo3(signature = (*, now = None))]
+    fn check(&self, now: Option<i64>) -> PyResult<i64> {
+        self.enforce(now.unwrap_or_else(self::now))
+            .map(|(_, remaining)| remaining)
+    }
+
+    /// 剩余宽限秒数（不写入状态、不抛出回拨异常，供界面展示）；从未联网时为 None，已用尽时为 0
+    #[pyo3(signature = (*, now = None))]
+    fn remaining(&self, now: Option<i64>) -> PyResult<Option<i64>> {
+        let now = now.unwrap_or_else(self::now);
+        Ok(self.load()?.map(|state| {
+            let elapsed = now.max(state.last_seen) - state.last_online;
+            (self.grace_seconds - elapsed).max(0)
+        }))
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "OfflineGrace(path={:?}, grace_seconds={}, rollback_tolerance={})",
+            self.path, self.grace_seconds, self.rollback_tolerance
+        )
+    }
+}
+
+/// 生成一次性的时间凭证挑战，随取密钥请求发送给服务端，服务端签发的时间凭证须包含该挑战
+#[pyfunction]
+pub fn time_challenge() -> String {
+    let mut nonce = [0u8; TIME_NONCE_LEN];
+    OsRng.fill_bytes(&mut nonce);
+    let mut pending = PENDING_CHALLENGES.lock().unwrap_or_else(|e| e.into_inner());
+    if pending.len() >= MAX_PENDING_CHALLENGES {
+        pending.remove(0);
+    }
+    pending.push(nonce);
+    URL_SAFE_NO_PAD.encode(nonce)
+}
+
+/// 签发时间凭证（服务端使用），随策略密钥一起下发给客户端确认联网。
+/// `machine` 为客户端 `machine_id()` 的结果，`nonce` 为客户端 `time_challenge()` 的结果，
+/// `issued_at` 省略时为当前时间（Unix 秒）
+#[pyfunction]
+#[pyo3(signature = (signing_key, machine, nonce, *, issued_at = None))]
+pub fn issue_time_token(
+    signing_key: &str,
+    machine: &str,
+    nonce: &str,
+    issued_at: Option<i64>,
+) -> PyResult<String> {
+    let signing_key = parse_signing_key(signing_key)?;
+    let mut payload = vec![TIME_TOKEN_VERSION];
+    payload.extend_from_slice(&issued_at.unwrap_or_else(now).to_le_bytes());
+    payload.extend_from_slice(&parse_nonce(nonce)?);
+    push_str(&mut payload, machine)?;
+    Ok(sign_token(
+        &signing_key,
+        TIME_TOKEN_PREFIX,
+        TIME_TOKEN_LABEL,
+        &payload,
+    ))
+}
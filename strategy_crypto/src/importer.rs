@@ -0,0 +1,125 @@
+//! 从加密策略包导入模块
+//!
+//! `BundleImporter` 同时实现 `sys.meta_path` 的查找器与加载器接口：`find_spec` 只查询已认证的
+//! manifest，`exec_module` 才把对应条目解密到安全缓冲区、编译并在模块命名空间中执行。
+//! 源码只在编译期间以 bytearray 形式出现在 Python 堆上，编译后立即清零；
+//! 加载器不实现 `get_source`，明文既不落盘，也不会作为返回值交给调用方。
+
+use pyo3::exceptions::{PyImportError, PySyntaxError};
+use pyo3::prelude::*;
+use pyo3::types::{IntoPyDict, PyByteArray};
+use zeroize::Zeroize;
+
+use crate::bundle::Bundle;
+
+/// 模块的 `origin`，同时用作 traceback 中的文件名
+fn origin(path: &str) -> String {
+    format!("bundle://{path}")
+}
+
+/// 编译并在模块命名空间中执行源码，编译完成后清零源码副本
+fn exec_source(module: &Bound<'_, PyModule>, source: &[u8], filename: &str) -> PyResult<()> {
+    let py = module.py();
+    let builtins = py.import_bound("builtins")?;
+    let buf = PyByteArray::new_bound(py, source);
+    let code = builtins.getattr("compile")?.call1((&buf, filename, "exec"));
+    // SAFETY: buf 只在本函数内持有，compile 已返回，不存在其他对缓冲区的引用
+    unsafe { buf.as_bytes_mut() }.zeroize();
+    let code = code.inspect_err(|err| {
+        // SyntaxError 会携带出错的源码行，清掉以免明文随异常传播
+        if err.is_instance_of::<PySyntaxError>(py) {
+            let _ = err.value_bound(py).setattr("text", py.None());
+        }
+    })?;
+    builtins.getattr("exec")?.call1((code, module.dict()))?;
+    Ok(())
+}
+
+/// 加密策略包的导入钩子，`install()` 后即可 `import mystrategy.signals`
+#[pyclass(module = "strategy_crypto")]
+pub struct BundleImporter {
+    bundle: Py<Bundle>,
+}
+
+#[pymethods]
+impl BundleImporter {
+    #[new]
+    fn new(bundle: Py<Bundle>) -> Self {
+        BundleImporter { bundle }
+    }
+
+    #[getter]
+    fn bundle(&self, py: Python<'_>) -> Py<Bundle> {
+        self.bundle.clone_ref(py)
+    }
+
+    #[getter]
+    fn entry_point(&self, py: Python<'_>) -> String {
+        self.bundle.borrow(py).entry_point().to_string()
+    }
+
+    /// 包中没有该模块时返回 None，交给后续查找器处理
+    #[pyo3(signature = (fullname, _path = None, _target = None))]
+    fn find_spec<'py>(
+        slf: &Bound<'py, Self>,
+        fullname: &str,
+        _path: Option<&Bound<'py, PyAny>>,
+        _target: Option<&Bound<'py, PyAny>>,
+    ) -> PyResult<Option<Bound<'py, PyAny>>> {
+        let py = slf.py();
+        let bundle = slf.borrow().bundle.clone_ref(py);
+        let bundle = bundle.borrow(py);
+        let Some((path, is_package)) = bundle.find_module(fullname) else {
+            return Ok(None);
+        };
+        let kwargs = [
+            ("origin", origin(path).into_py(py)),
+            ("is_package", is_package.into_py(py)),
+        ]
+        .into_py_dict_bound(py);
+        py.import_bound("importlib.util")?
+            .call_method("spec_from_loader", (fullname, slf), Some(&kwargs))
+            .map(Some)
+    }
+
+    /// 使用默认的模块创建方式
+    fn create_module(&self, _spec: &Bound<'_, PyAny>) -> Option<PyObject> {
+        None
+    }
+
+    fn exec_module(&self, module: &Bound<'_, PyModule>) -> PyResult<()> {
+        let py = module.py();
+        let name: String = module.getattr("__spec__")?.getattr("name")?.extract()?;
+        let bundle = self.bundle.borrow(py);
+        let (path, _) = bundle
+            .find_module(&name)
+            .ok_or_else(|| PyImportError::new_err(format!("策略包中没有模块 {name}")))?;
+        let source = bundle.read_entry(path)?;
+        exec_source(module, &source, &origin(path))
+    }
+
+    /// 插入到 `sys.meta_path` 最前面，已安装时不重复插入
+    fn install(slf: &Bound<'_, Self>) -> PyResult<()> {
+        let meta_path = slf.py().import_bound("sys")?.getattr("meta_path")?;
+        if !meta_path.contains(slf)? {
+            meta_path.call_method1("insert", (0, slf))?;
+        }
+        Ok(())
+    }
+
+    /// 从 `sys.meta_path` 移除；已导入的模块仍保留在 `sys.modules` 中
+    fn uninstall(slf: &Bound<'_, Self>) -> PyResult<()> {
+        let meta_path = slf.py().import_bound("sys")?.getattr("meta_path")?;
+        while meta_path.contains(slf)? {
+            meta_path.call_method1("remove", (slf,))?;
+        }
+        Ok(())
+    }
+
+    fn __repr__(&self, py: Python<'_>) -> String {
+        format!(
+            "BundleImporter(entry_point={:?})",
+            self.bundle.borrow(py).entry_point()
+        )
+    }
+}
@@ -0,0 +1,281 @@
+//! 密钥派生
+//!
+//! - 口令派生（Argon2id）：盐和代价参数写入容器头部扩展区，解密时按头部记录的参数重新派生密钥；
+//!   低于最低代价或超过上限的参数一律拒绝，防止降级或借超大参数耗尽内存。
+//! - 主密钥派生（HKDF-SHA256）：以策略上下文（strategy_id / version / purpose 等）作为 info，
+//!   由同一个主密钥确定性地派生出每个策略的子密钥。
+
+use argon2::{Algorithm as Argon2Algorithm, Argon2, Params, Version};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use hkdf::Hkdf;
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::PyBytes;
+use rand::rngs::OsRng;
+use rand::RngCore;
+use sha2::Sha256;
+
+use crate::cipher::{open_container, seal_signed};
+use crate::compress::{Codec, Compression};
+use crate::container::{self, Algorithm, Context, Header};
+use crate::error::{InvalidKeyError, MalformedCiphertextError, UnsupportedVersionError};
+use crate::key::{parse_key, Key};
+use crate::license;
+use crate::padding::Padding;
+use crate::secret::SecretKey;
+use crate::sign::parse_signing_key;
+
+pub const KDF_ARGON2ID: u8 = 1;
+
+/// HKDF info 前缀，区分本库派生的子密钥与其他用途
+const HKDF_LABEL: &[u8] = b"bullet-trade/strategy-key/v1";
+
+pub const SALT_LEN: usize = 16;
+
+/// 最低代价：参考 OWASP 推荐的 Argon2id 下限（19 MiB、2 轮）
+pub const MIN_M_COST: u32 = 19 * 1024;
+pub const MIN_T_COST: u32 = 2;
+/// 解密时接受的上限，避免恶意头部要求过大的内存或迭代次数
+pub const MAX_M_COST: u32 = 1024 * 1024;
+pub const MAX_T_COST: u32 = 32;
+pub const MAX_P_COST: u32 = 16;
+
+pub const DEFAULT_M_COST: u32 = 64 * 1024;
+pub const DEFAULT_T_COST: u32 = 3;
+pub const DEFAULT_P_COST: u32 = 1;
+
+/// kdf_id(1) | m_cost(4) | t_cost(4) | p_cost(4) | salt(16)
+const ENCODED_LEN: usize = 1 + 4 + 4 + 4 + SALT_LEN;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KdfParams {
+    pub m_cost: u32,
+    pub t_cost: u32,
+    pub p_cost: u32,
+    pub salt: [u8; SALT_LEN],
+}
+
+impl KdfParams {
+    pub fn new(m_cost: u32, t_cost: u32, p_cost: u32) -> PyResult<Self> {
+        let mut salt = [0u8; SALT_LEN];
+        OsRng.fill_bytes(&mut salt);
+        let params = KdfParams {
+            m_cost,
+            t_cost,
+            p_cost,
+            salt,
+        };
+        params
+            .check()
+            .map_err(|e| PyValueError::new_err(e.to_string()))?;
+        Ok(params)
+    }
+
+    fn check(&self) -> Result<(), String> {
+        if !(MIN_M_COST..=MAX_M_COST).contains(&self.m_cost) {
+            return Err(format!(
+                "Argon2id m_cost 需在 {MIN_M_COST}..={MAX_M_COST} KiB 之间，实际为 {}",
+                self.m_cost
+            ));
+        }
+        if !(MIN_T_COST..=MAX_T_COST).contains(&self.t_cost) {
+            return Err(format!(
+                "Argon2id t_cost 需在 {MIN_T_COST}..={MAX_T_COST} 之间，实际为 {}",
+                self.t_cost
+            ));
+        }
+        if !(1..=MAX_P_COST).contains(&self.p_cost) {
+            return Err(format!(
+                "Argon2id p_cost 需在 1..={MAX_P_COST} 之间，实际为 {}",
+                self.p_cost
+            ));
+        }
+        Ok(())
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(ENCODED_LEN);
+        out.push(KDF_ARGON2ID);
+        out.extend_from_slice(&self.m_cost.to_le_bytes());
+        out.extend_from_slice(&self.t_cost.to_le_bytes());
+        out.extend_from_slice(&self.p_cost.to_le_bytes());
+        out.extend_from_slice(&self.salt);
+        out
+    }
+
+    pub fn decode(raw: &[u8]) -> PyResult<Self> {
+        if raw.len() != ENCODED_LEN {
+            return Err(MalformedCiphertextError::new_err("口令派生参数长度错误"));
+        }
+        if raw[0] != KDF_ARGON2ID {
+            return Err(UnsupportedVersionError::new_err(format!(
+                "不支持的口令派生算法: {}",
+                raw[0]
+            )));
+        }
+        let u32_at = |i: usize| u32::from_le_bytes([raw[i], raw[i + 1], raw[i + 2], raw[i + 3]]);
+        let mut salt = [0u8; SALT_LEN];
+        salt.copy_from_slice(&raw[13..]);
+        Ok(KdfParams {
+            m_cost: u32_at(1),
+            t_cost: u32_at(5),
+            p_cost: u32_at(9),
+            salt,
+        })
+    }
+
+    pub fn derive(&self, password: &str) -> PyResult<Key> {
+        if password.is_empty() {
+            return Err(InvalidKeyError::new_err("口令不能为空"));
+        }
+        self.check().map_err(UnsupportedVersionError::new_err)?;
+        let params = Params::new(self.m_cost, self.t_cost, self.p_cost, Some(32))
+            .map_err(|e| UnsupportedVersionError::new_err(format!("Argon2id 参数无效: {e}")))?;
+        let mut key = SecretKey::zeroed();
+        Argon2::new(Argon2Algorithm::Argon2id, Version::V0x13, params)
+            .hash_password_into(password.as_bytes(), &self.salt, &mut key[..])
+            .map_err(|e| InvalidKeyError::new_err(format!("口令派生密钥失败: {e}")))?;
+        Ok(key)
+    }
+}
+
+/// 用口令加密：Argon2id 派生 AES-256 密钥，盐和代价参数写入头部
+#[pyfunction]
+#[pyo3(signature = (
+    password,
+    plaintext,
+    *,
+    context = None,
+    algorithm = None,
+    compression = None,
+    padding = None,
+    signing_key = None,
+    m_cost = DEFAULT_M_COST,
+    t_cost = DEFAULT_T_COST,
+    p_cost = DEFAULT_P_COST,
+))]
+pub fn encrypt_with_password(
+    py: Python<'_>,
+    password: &str,
+    plaintext: &[u8],
+    context: Option<Context>,
+    algorithm: Option<&str>,
+    compression: Option<&str>,
+    padding: Option<&str>,
+    signing_key: Option<&str>,
+    m_cost: u32,
+    t_cost: u32,
+    p_cost: u32,
+) -> PyResult<Py<PyBytes>> {
+    let alg = Algorithm::from_name(algorithm)?;
+    let kdf = KdfParams::new(m_cost, t_cost, p_cost)?;
+    let key = py.allow_threads(|| kdf.derive(password))?;
+    let mut header = Header::new(alg, None)?;
+    header.context = context;
+    header.kdf = Some(kdf);
+    header.compression = Codec::from_name(compression)?.map(Compression::new);
+    header.padding = Padding::from_spec(padding)?;
+    let signing_key = signing_key.map(parse_signing_key).transpose()?;
+    let out = seal_signed(&key, &header, plaintext, signing_key.as_ref())?;
+    Ok(PyBytes::new_bound(py, &out).unbind())
+}
+
+/// 用口令解密 `encrypt_with_password` 的输出；给定 `expected` 时同时校验关联数据
+#[pyfunction]
+#[pyo3(signature = (password, blob, *, expected = None))]
+pub fn decrypt_with_password(
+    py: Python<'_>,
+    password: &str,
+    blob: &[u8],
+    expected: Option<Context>,
+) -> PyResult<Py<PyBytes>> {
+    license::check_unlicensed_allowed()?;
+    let (header, header_len) = Header::parse(blob)?;
+    let Some(kdf) = &header.kdf else {
+        return Err(InvalidKeyError::new_err(
+            "该密文不是口令加密，请使用 decrypt_bytes",
+        ));
+    };
+    let key = py.allow_threads(|| kdf.derive(password))?;
+    let pt = open_container(&key, blob, &header, header_len)?;
+    if let Some(expected) = &expected {
+        container::check_context(Some(&header), expected, false)?;
+    }
+    Ok(PyBytes::new_bound(py, &pt).unbind())
+}
+
+/// 由主密钥和策略上下文派生子密钥；上下文必须包含 `strategy_id`
+pub fn derive_subkey(master: &Key, context: &Context) -> PyResult<Key> {
+    if !context.contains_key("strategy_id") {
+        return Err(PyValueError::new_err("派生上下文必须包含 strategy_id"));
+    }
+    let mut info = HKDF_LABEL.to_vec();
+    info.extend_from_slice(&container::encode_context(context)?);
+    let mut key = SecretKey::zeroed();
+    Hkdf::<Sha256>::new(None, &master[..])
+        .expand(&info, &mut key[..])
+        .map_err(|e| InvalidKeyError::new_err(format!("HKDF 派生失败: {e}")))?;
+    Ok(key)
+}
+
+/// HKDF-SHA256 派生策略子密钥，返回 `b64:` 前缀的密钥字符串，可直接作为 key 传给其他接口
+#[pyfunction]
+pub fn derive_key(master: &str, context: Context) -> PyResult<String> {
+    let key = derive_subkey(&parse_key(master)?, &context)?;
+    Ok(format!("b64:{}", STANDARD.encode(&key[..])))
+}
+
+/// 用主密钥按 `context` 派生子密钥加密，`context` 同时写入头部作为关联数据
+#[pyfunction]
+#[pyo3(signature = (master, plaintext, context, *, key_id = None, algorithm = None, compression = None, padding = None, signing_key = None))]
+pub fn encrypt_bytes_derived(
+    py: Python<'_>,
+    master: &str,
+    plaintext: &[u8],
+    context: Context,
+    key_id: Option<&str>,
+    algorithm: Option<&str>,
+    compression: Option<&str>,
+    padding: Option<&str>,
+    signing_key: Option<&str>,
+) -> PyResult<Py<PyBytes>> {
+    let alg = Algorithm::from_name(algorithm)?;
+    let key = derive_subkey(&parse_key(master)?, &context)?;
+    let mut header = Header::new(alg, key_id)?;
+    header.context = Some(context);
+    header.compression = Codec::from_name(compression)?.map(Compression::new);
+    header.padding = Padding::from_spec(padding)?;
+    let signing_key = signing_key.map(parse_signing_key).transpose()?;
+    let out = seal_signed(&key, &header, plaintext, signing_key.as_ref())?;
+    Ok(PyBytes::new_bound(py, &out).unbind())
+}
+
+/// 按头部记录的上下文从主密钥派生子密钥解密；给定 `expected` 时同时校验关联数据
+#[pyfunction]
+#[pyo3(signature = (master, blob, *, expected = None))]
+pub fn decrypt_bytes_derived(
+    py: Python<'_>,
+    master: &str,
+    blob: &[u8],
+    expected: Option<Context>,
+) -> PyResult<Py<PyBytes>> {
+    license::check_unlicensed_allowed()?;
+    let master = parse_key(master)?;
+    let (header, header_len) = Header::parse(blob)?;
+    if header.kdf.is_some() {
+        return Err(InvalidKeyError::new_err(
+            "该密文使用口令加密，请使用 decrypt_with_password",
+        ));
+    }
+    let Some(context) = &header.context else {
+        return Err(MalformedCiphertextError::new_err(
+            "密文未记录派生上下文，无法从主密钥派生子密钥",
+        ));
+    };
+    let key = derive_subkey(&master, context)?;
+    let pt = open_container(&key, blob, &header, header_len)?;
+    if let Some(expected) = &expected {
+        container::check_context(Some(&header), expected, false)?;
+    }
+    Ok(PyBytes::new_bound(py, &pt).unbind())
+}
@@ -0,0 +1,166 @@
+//! 密钥解析
+//!
+//! 支持带前缀的密钥字符串：
+//!
+//! - `hex:<64 个十六进制字符>`
+//! - `b64:<标准 base64，含填充>`
+//! - `raw:<32 字节 UTF-8 字符串>`
+//! - `file:<路径>`：文件内容恰为 32 字节时视为原始密钥，否则去除首尾空白后按带前缀的字符串解析
+//! - `sealed:<base64>`：封装给本机设备公钥的密钥，用 `set_device_key` 配置的设备私钥解封
+//!
+//! 未带前缀的字符串按旧规则依次尝试 原始/hex/base64；严格模式下直接拒绝。
+
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use pyo3::prelude::*;
+
+use zeroize::Zeroizing;
+
+use crate::error::{InvalidKeyError, KeyNotConfiguredError};
+use crate::secret::SecretKey;
+use crate::wrap;
+
+pub type Key = SecretKey;
+
+pub const KEY_LEN: usize = 32;
+
+/// 旧版内置密钥（32 字节），仅用于把历史密文迁移到正式密钥
+#[cfg(feature = "legacy-default-key")]
+const DEFAULT_KEY: &[u8; KEY_LEN] = b"agfdsfsdafsdafdsafsdafdsafdfghdy";
+
+type Parser = fn(&str) -> Result<Key, String>;
+
+static STRICT: AtomicBool = AtomicBool::new(false);
+
+/// 解析失败时记录每种编码的尝试结果
+#[derive(Debug, Default)]
+pub struct KeyParseError {
+    attempts: Vec<(&'static str, String)>,
+}
+
+impl KeyParseError {
+    fn single(encoding: &'static str, reason: impl Into<String>) -> Self {
+        KeyParseError {
+            attempts: vec![(encoding, reason.into())],
+        }
+    }
+}
+
+impl fmt::Display for KeyParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "无法解析密钥，需为 32 字节")?;
+        for (i, (encoding, reason)) in self.attempts.iter().enumerate() {
+            let sep = if i == 0 { "：" } else { "；" };
+            write!(f, "{sep}{encoding} {reason}")?;
+        }
+        Ok(())
+    }
+}
+
+impl From<KeyParseError> for PyErr {
+    fn from(err: KeyParseError) -> PyErr {
+        InvalidKeyError::new_err(err.to_string())
+    }
+}
+
+fn to_key(raw: &[u8]) -> Result<Key, String> {
+    if raw.len() != KEY_LEN {
+        return Err(format!("长度为 {} 字节，需 {KEY_LEN} 字节", raw.len()));
+    }
+    let mut key = SecretKey::zeroed();
+    key.copy_from_slice(raw);
+    Ok(key)
+}
+
+fn parse_hex(s: &str) -> Result<Key, String> {
+    let raw = Zeroizing::new(hex::decode(s).map_err(|e| format!("解码失败（{e}）"))?);
+    to_key(&raw)
+}
+
+fn parse_b64(s: &str) -> Result<Key, String> {
+    let raw = Zeroizing::new(STANDARD.decode(s).map_err(|e| format!("解码失败（{e}）"))?);
+    to_key(&raw)
+}
+
+fn parse_raw(s: &str) -> Result<Key, String> {
+    to_key(s.as_bytes())
+}
+
+fn parse_file(path: &str, strict: bool) -> Result<Key, KeyParseError> {
+    let content = Zeroizing::new(
+        std::fs::read(path)
+            .map_err(|e| KeyParseError::single("file", format!("读取 {path} 失败（{e}）")))?,
+    );
+    if let Ok(key) = to_key(&content) {
+        return Ok(key);
+    }
+    let text = std::str::from_utf8(&content)
+        .map_err(|_| KeyParseError::single("file", format!("{path} 既不是 32 字节也不是文本")))?
+        .trim();
+    if text.starts_with("file:") {
+        return Err(KeyParseError::single("file", "密钥文件不能再引用其他文件"));
+    }
+    parse_key_str(text, strict)
+}
+
+fn parse_key_str(s: &str, strict: bool) -> Result<Key, KeyParseError> {
+    if let Some(rest) = s.strip_prefix("hex:") {
+        return parse_hex(rest).map_err(|e| KeyParseError::single("hex", e));
+    }
+    if let Some(rest) = s.strip_prefix("b64:") {
+        return parse_b64(rest).map_err(|e| KeyParseError::single("b64", e));
+    }
+    if let Some(rest) = s.strip_prefix("raw:") {
+        return parse_raw(rest).map_err(|e| KeyParseError::single("raw", e));
+    }
+    if let Some(rest) = s.strip_prefix("file:") {
+        return parse_file(rest, strict);
+    }
+    if let Some(rest) = s.strip_prefix("sealed:") {
+        return wrap::unwrap_key(rest).map_err(|e| KeyParseError::single("sealed", e));
+    }
+    if strict {
+        return Err(KeyParseError::single(
+            "strict",
+            "严格模式要求 hex:/b64:/raw:/file:/sealed: 前缀",
+        ));
+    }
+
+    // 兼容旧版：依次尝试 原始 32 字节 / hex / base64
+    let mut err = KeyParseError::default();
+    let parsers: [(&'static str, Parser); 3] =
+        [("raw", parse_raw), ("hex", parse_hex), ("b64", parse_b64)];
+    for (encoding, parser) in parsers {
+        match parser(s) {
+            Ok(key) => return Ok(key),
+            Err(reason) => err.attempts.push((encoding, reason)),
+        }
+    }
+    Err(err)
+}
+
+/// 旧版内置密钥，直接由字节构造，不经过密钥字符串解析（严格模式下同样可用）
+#[cfg(feature = "legacy-default-key")]
+pub fn default_key() -> Key {
+    let mut key = SecretKey::zeroed();
+    key.copy_from_slice(DEFAULT_KEY);
+    key
+}
+
+/// 将带前缀（或旧版无前缀）的密钥字符串解析为 32 字节 key
+pub fn parse_key(key_str: &str) -> PyResult<Key> {
+    if key_str.is_empty() {
+        return Err(KeyNotConfiguredError::new_err(
+            "未配置密钥：请设置 STRATEGY_KEY 或显式传入 key",
+        ));
+    }
+    Ok(parse_key_str(key_str, STRICT.load(Ordering::Relaxed))?)
+}
+
+/// 开启后拒绝未带 hex:/b64:/raw:/file:/sealed: 前缀的密钥字符串
+#[pyfunction]
+pub fn set_strict_keys(enabled: bool) {
+    STRICT.store(enabled, Ordering::Relaxed);
+}
@@ -0,0 +1,288 @@
+//! 多密钥密钥环，支持按 key_id 选择密钥与轮换
+//!
+//! 密钥环文件与环境变量使用相同的条目格式 `key_id[@算法]=<密钥字符串>`，密钥字符串支持
+//! `key` 模块的全部编码，`@算法` 可选，指定该密钥加密时默认使用的算法（如 `k1@aes-256-gcm-siv=hex:...`）。
+//! 文件中每行一条，`#` 开头为注释；环境变量中以 `,` 或 `;` 分隔。
+//! 第一条为主密钥（加密时使用），轮换时把新密钥放在最前面即可。
+
+use pyo3::exceptions::{PyKeyError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::PyBytes;
+
+use crate::cipher::{open, seal_signed};
+use crate::compress::{Codec, Compression};
+use crate::container::{self, Algorithm, Context, Header};
+use crate::error::{AuthenticationError, InvalidKeyError, KeyNotConfiguredError, SignatureError};
+use crate::grace::OfflineGrace;
+use crate::key::{parse_key, Key};
+use crate::license::{self, License};
+use crate::padding::Padding;
+use crate::secret::SecretBytes;
+use crate::sign::parse_signing_key;
+
+pub const DEFAULT_ENV_VAR: &str = "STRATEGY_KEYS";
+
+#[pyclass(module = "strategy_crypto")]
+#[derive(Default)]
+pub struct Keyring {
+    /// 按加入顺序保存，首项为主密钥
+    entries: Vec<KeyEntry>,
+}
+
+pub struct KeyEntry {
+    pub id: String,
+    pub key: Key,
+    /// 该密钥加密时默认使用的算法；调用时显式指定的算法优先
+    pub alg: Option<Algorithm>,
+}
+
+impl Keyring {
+    fn insert(
+        &mut self,
+        key_id: &str,
+        key: &str,
+        alg: Option<Algorithm>,
+        primary: bool,
+    ) -> PyResult<()> {
+        if key_id.is_empty() || key_id.len() > u8::MAX as usize {
+            return Err(PyValueError::new_err("key_id 长度需为 1..=255 字节"));
+        }
+        let key = parse_key(key)?;
+        self.entries.retain(|e| e.id != key_id);
+        let entry = KeyEntry {
+            id: key_id.to_string(),
+            key,
+            alg,
+        };
+        if primary {
+            self.entries.insert(0, entry);
+        } else {
+            self.entries.push(entry);
+        }
+        Ok(())
+    }
+
+    fn parse_entries<'a>(entries: impl Iterator<Item = &'a str>, source: &str) -> PyResult<Self> {
+        let mut ring = Keyring::default();
+        for (i, entry) in entries.enumerate() {
+            let entry = entry.trim();
+            if entry.is_empty() || entry.starts_with('#') {
+                continue;
+            }
+            let Some((name, key)) = entry.split_once('=') else {
+                return Err(PyValueError::new_err(format!(
+                    "{source} 第 {} 项格式错误，应为 key_id[@算法]=<密钥>",
+                    i + 1
+                )));
+            };
+            let (key_id, alg) = match name.trim().split_once('@') {
+                Some((key_id, alg)) => {
+                    (key_id.trim(), Some(Algorithm::from_name(Some(alg.trim()))?))
+                }
+                None => (name.trim(), None),
+            };
+            if ring.get(key_id).is_some() {
+                return Err(PyValueError::new_err(format!(
+                    "{source} 中 key_id 重复: {key_id}"
+                )));
+            }
+            ring.insert(key_id, key.trim(), alg, false)?;
+        }
+        Ok(ring)
+    }
+
+    pub fn get(&self, key_id: &str) -> Option<&KeyEntry> {
+        self.entries.iter().find(|e| e.id == key_id)
+    }
+
+    pub fn primary_entry(&self) -> PyResult<&KeyEntry> {
+        self.entries
+            .first()
+            .ok_or_else(|| KeyNotConfiguredError::new_err("密钥环为空"))
+    }
+
+    /// 解密：有 key_id 时按 id 选择密钥，旧版或未标记 key_id 的密文依次尝试所有密钥
+    pub fn open(&self, py: Python<'_>, blob: &[u8]) -> PyResult<(Option<Header>, SecretBytes)> {
+        let key_id = if container::has_magic(blob) {
+            Header::parse(blob)?.0.key_id
+        } else {
+            String::new()
+        };
+        if !key_id.is_empty() {
+            let entry = self.get(&key_id).ok_or_else(|| {
+                InvalidKeyError::new_err(format!("密钥环中没有 key_id 为 {key_id:?} 的密钥"))
+            })?;
+            return open(&entry.key, blob);
+        }
+        self.primary_entry()?;
+        for entry in &self.entries {
+            match open(&entry.key, blob) {
+                // 签名错误与所用密钥无关，直接抛出，不能当作“换个密钥再试”
+                Err(e) if e.is_instance_of::<SignatureError>(py) => return Err(e),
+                Err(e) if e.is_instance_of::<AuthenticationError>(py) => continue,
+                other => return other,
+            }
+        }
+        Err(AuthenticationError::new_err(
+            "认证失败：密钥环中没有能解密该密文的密钥",
+        ))
+    }
+}
+
+#[pymethods]
+impl Keyring {
+    #[new]
+    fn py_new() -> Self {
+        Keyring::default()
+    }
+
+    /// 从密钥环文件加载，每行一条 `key_id=<密钥>`
+    #[staticmethod]
+    fn from_file(path: &str) -> PyResult<Self> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| InvalidKeyError::new_err(format!("读取密钥环文件 {path} 失败: {e}")))?;
+        Keyring::parse_entries(content.lines(), path)
+    }
+
+    /// 从环境变量加载，条目以 `,` 或 `;` 分隔
+    #[staticmethod]
+    #[pyo3(signature = (var = DEFAULT_ENV_VAR))]
+    fn from_env(var: &str) -> PyResult<Self> {
+        let value = std::env::var(var).unwrap_or_default();
+        if value.trim().is_empty() {
+            return Err(KeyNotConfiguredError::new_err(format!(
+                "未配置密钥：环境变量 {var} 为空"
+            )));
+        }
+        Keyring::parse_entries(value.split([',', ';']), var)
+    }
+
+    /// 加入密钥；已存在同名 key_id 时替换。`primary=True` 时设为主密钥，
+    /// `algorithm` 指定该密钥加密时默认使用的算法
+    #[pyo3(signature = (key_id, key, *, primary = false, algorithm = None))]
+    fn add(
+        &mut self,
+        key_id: &str,
+        key: &str,
+        primary: bool,
+        algorithm: Option<&str>,
+    ) -> PyResult<()> {
+        let alg = algorithm
+            .map(|name| Algorithm::from_name(Some(name)))
+            .transpose()?;
+        self.insert(key_id, key, alg, primary)
+    }
+
+    fn remove(&mut self, key_id: &str) -> PyResult<()> {
+        let before = self.entries.len();
+        self.entries.retain(|e| e.id != key_id);
+        if self.entries.len() == before {
+            return Err(PyKeyError::new_err(key_id.to_string()));
+        }
+        Ok(())
+    }
+
+    fn set_primary(&mut self, key_id: &str) -> PyResult<()> {
+        let pos = self
+            .entries
+            .iter()
+            .position(|e| e.id == key_id)
+            .ok_or_else(|| PyKeyError::new_err(key_id.to_string()))?;
+        let entry = self.entries.remove(pos);
+        self.entries.insert(0, entry);
+        Ok(())
+    }
+
+    #[getter]
+    fn primary(&self) -> Option<String> {
+        self.entries.first().map(|e| e.id.clone())
+    }
+
+    fn key_ids(&self) -> Vec<String> {
+        self.entries.iter().map(|e| e.id.clone()).collect()
+    }
+
+    fn __len__(&self) -> usize {
+        self.entries.len()
+    }
+
+    fn __contains__(&self, key_id: &str) -> bool {
+        self.get(key_id).is_some()
+    }
+
+    fn __repr__(&self) -> String {
+        format!("Keyring(key_ids={:?})", self.key_ids())
+    }
+
+    /// 各密钥的默认算法，未指定时为 None
+    fn algorithms(&self) -> Vec<(String, Option<&'static str>)> {
+        self.entries
+            .iter()
+            .map(|e| (e.id.clone(), e.alg.map(Algorithm::name)))
+            .collect()
+    }
+
+    /// 用主密钥（或指定 `key_id` 的密钥）加密，并把 key_id 写入头部。
+    ///
+    /// 算法优先取 `algorithm` 参数，其次取该密钥条目的默认算法，最后为 AES-256-GCM
+    #[pyo3(signature = (plaintext, *, context = None, key_id = None, algorithm = None, compression = None, padding = None, signing_key = None))]
+    fn encrypt(
+        &self,
+        py: Python<'_>,
+        plaintext: &[u8],
+        context: Option<Context>,
+        key_id: Option<&str>,
+        algorithm: Option<&str>,
+        compression: Option<&str>,
+        padding: Option<&str>,
+        signing_key: Option<&str>,
+    ) -> PyResult<Py<PyBytes>> {
+        let entry = match key_id {
+            Some(id) => self
+                .get(id)
+                .ok_or_else(|| PyKeyError::new_err(id.to_string()))?,
+            None => self.primary_entry()?,
+        };
+        let alg = match algorithm {
+            Some(name) => Algorithm::from_name(Some(name))?,
+            None => entry.alg.unwrap_or_default(),
+        };
+        let mut header = Header::new(alg, Some(&entry.id))?;
+        header.context = context;
+        header.compression = Codec::from_name(compression)?.map(Compression::new);
+        header.padding = Padding::from_spec(padding)?;
+        let signing_key = signing_key.map(parse_signing_key).transpose()?;
+        let out = seal_signed(&entry.key, &header, plaintext, signing_key.as_ref())?;
+        Ok(PyBytes::new_bound(py, &out).unbind())
+    }
+
+    /// 按头部 key_id 选择密钥解密；给定 `expected` 时同时校验关联数据。
+    ///
+    /// 配置了授权公钥时必须传入 `license` 与 `mode`，授权须覆盖密文绑定的 strategy_id
+    #[pyo3(signature = (blob, *, expected = None, allow_unbound = false, license = None, mode = None, grace = None))]
+    fn decrypt(
+        &self,
+        py: Python<'_>,
+        blob: &[u8],
+        expected: Option<Context>,
+        allow_unbound: bool,
+        license: Option<PyRef<'_, License>>,
+        mode: Option<&str>,
+        grace: Option<PyRef<'_, OfflineGrace>>,
+    ) -> PyResult<Py<PyBytes>> {
+        if license.is_none() {
+            license::check_unlicensed_allowed()?;
+        }
+        let (header, pt) = self.open(py, blob)?;
+        if let Some(expected) = &expected {
+            container::check_context(header.as_ref(), expected, allow_unbound)?;
+        }
+        license::check_bound_license(
+            license.as_deref(),
+            mode,
+            grace.as_deref(),
+            header.as_ref().and_then(|header| header.context.as_ref()),
+        )?;
+        Ok(PyBytes::new_bound(py, &pt).unbind())
+    }
+}
@@ -1,87 +1,185 @@
-use aes_gcm::aead::{Aead, KeyInit};
-use aes_gcm::{Aes256Gcm, Nonce}; // 96-bit nonce
-use base64::{engine::general_purpose::STANDARD, Engine};
-use pyo3::exceptions::PyValueError;
+// Python 接口以关键字参数暴露可选项，参数个数随之增长
+#![allow(clippy::too_many_arguments)]
+
+mod bundle;
+mod cipher;
+mod compress;
+mod container;
+mod error;
+mod files;
+mod grace;
+mod importer;
+mod kdf;
+mod key;
+mod keyring;
+mod license;
+mod padding;
+mod revocation;
+mod rotate;
+mod secret;
+mod sign;
+mod stream;
+mod wrap;
+
 use pyo3::prelude::*;
 use pyo3::types::PyBytes;
-use rand::rngs::OsRng;
-use rand::RngCore;
 
-// 默认内置密钥（32 字节）。仅供无环境变量场景使用，生产请覆盖。
-const DEFAULT_KEY: &str = "agfdsfsdafsdafdsafsdafdsafdfghdy";
+use cipher::{open, seal_signed};
+use compress::{Codec, Compression};
+use container::{Algorithm, Context, Header, FORMAT_VERSION};
+use key::parse_key;
+use padding::Padding;
+use sign::parse_signing_key;
+
+/// 输出格式见 `container` 模块：头部 + nonce + ciphertext||tag。
+///
+/// `algorithm` 可选 aes-256-gcm（默认）/ chacha20-poly1305 / xchacha20-poly1305 / aes-256-gcm-siv，
+/// 记录在头部中，解密时自动识别。`compression="zstd"` 时先压缩再加密，解密时自动解压。
+/// `padding` 可选 `pow2` / `block:<n>`，把密文长度补齐到对应档位以隐藏明文的确切大小。
+/// `signing_key` 为发布者 Ed25519 私钥，提供时在密文末尾附加发布者签名。
+#[pyfunction]
+#[pyo3(signature = (key, plaintext, *, key_id = None, algorithm = None, compression = None, padding = None, signing_key = None))]
+fn encrypt_bytes(
+    py: Python<'_>,
+    key: &str,
+    plaintext: &[u8],
+    key_id: Option<&str>,
+    algorithm: Option<&str>,
+    compression: Option<&str>,
+    padding: Option<&str>,
+    signing_key: Option<&str>,
+) -> PyResult<Py<PyBytes>> {
+    let mut header = Header::new(Algorithm::from_name(algorithm)?, key_id)?;
+    header.compression = Codec::from_name(compression)?.map(Compression::new);
+    header.padding = Padding::from_spec(padding)?;
+    let signing_key = signing_key.map(parse_signing_key).transpose()?;
+    let out = seal_signed(&parse_key(key)?, &header, plaintext, signing_key.as_ref())?;
+    Ok(PyBytes::new_bound(py, &out).unbind())
+}
 
-/// 将 hex/base64/原始 32 字节 key 字符串解析为 32 字节 key
-fn parse_key(key_str: &str) -> PyResult<[u8; 32]> {
-    let key_input = if key_str.is_empty() {
-        DEFAULT_KEY
-    } else {
-        key_str
-    };
+/// 同时支持容器格式与旧版无头格式 `nonce(12) || ciphertext || tag`
+#[pyfunction]
+fn decrypt_bytes(py: Python<'_>, key: &str, blob: &[u8]) -> PyResult<Py<PyBytes>> {
+    license::check_unlicensed_allowed()?;
+    let (_, pt) = open(&parse_key(key)?, blob)?;
+    Ok(PyBytes::new_bound(py, &pt).unbind())
+}
 
-    if key_input.len() == 32 {
-        // 视为原始 utf-8 长度 32；不推荐但兼容
-        let bytes = key_input.as_bytes();
-        if bytes.len() == 32 {
-            let mut k = [0u8; 32];
-            k.copy_from_slice(bytes);
-            return Ok(k);
-        }
-    }
-    if let Ok(raw) = hex::decode(key_input) {
-        if raw.len() == 32 {
-            let mut k = [0u8; 32];
-            k.copy_from_slice(&raw);
-            return Ok(k);
-        }
-    }
-    if let Ok(raw) = STANDARD.decode(key_input) {
-        if raw.len() == 32 {
-            let mut k = [0u8; 32];
-            k.copy_from_slice(&raw);
-            return Ok(k);
-        }
-    }
-    Err(PyValueError::new_err(
-        "STRATEGY_KEY 需为 32 字节 hex/base64/原始字符串",
-    ))
+/// 加密并把 `context`（如 strategy_id / version / licensee）写入头部作为关联数据
+#[pyfunction]
+#[pyo3(signature = (key, plaintext, context, *, key_id = None, algorithm = None, compression = None, padding = None, signing_key = None))]
+fn encrypt_bytes_with_context(
+    py: Python<'_>,
+    key: &str,
+    plaintext: &[u8],
+    context: Context,
+    key_id: Option<&str>,
+    algorithm: Option<&str>,
+    compression: Option<&str>,
+    padding: Option<&str>,
+    signing_key: Option<&str>,
+) -> PyResult<Py<PyBytes>> {
+    let mut header = Header::new(Algorithm::from_name(algorithm)?, key_id)?;
+    header.context = Some(context);
+    header.compression = Codec::from_name(compression)?.map(Compression::new);
+    header.padding = Padding::from_spec(padding)?;
+    let signing_key = signing_key.map(parse_signing_key).transpose()?;
+    let out = seal_signed(&parse_key(key)?, &header, plaintext, signing_key.as_ref())?;
+    Ok(PyBytes::new_bound(py, &out).unbind())
 }
 
-/// 输出格式：nonce(12 bytes) + ciphertext||tag
+/// 解密并要求密文绑定的上下文与 `expected` 一致。
+///
+/// `allow_unbound=True` 时接受旧版无头格式密文，用于兼容已分发的策略；容器格式必须绑定上下文。
 #[pyfunction]
-fn encrypt_bytes(py: Python<'_>, key: &str, plaintext: &[u8]) -> PyResult<Py<PyBytes>> {
-    let key_bytes = parse_key(key)?;
-    let cipher =
-        Aes256Gcm::new_from_slice(&key_bytes).map_err(|e| PyValueError::new_err(e.to_string()))?;
-    let mut nonce_bytes = [0u8; 12];
-    OsRng.fill_bytes(&mut nonce_bytes);
-    let nonce = Nonce::from_slice(&nonce_bytes);
-    let mut out = nonce_bytes.to_vec();
-    let ct = cipher
-        .encrypt(nonce, plaintext)
-        .map_err(|e| PyValueError::new_err(e.to_string()))?;
-    out.extend_from_slice(&ct);
-    Ok(PyBytes::new(py, &out).into())
+#[pyo3(signature = (key, blob, expected, *, allow_unbound = false))]
+fn decrypt_bytes_with_context(
+    py: Python<'_>,
+    key: &str,
+    blob: &[u8],
+    expected: Context,
+    allow_unbound: bool,
+) -> PyResult<Py<PyBytes>> {
+    license::check_unlicensed_allowed()?;
+    let (header, pt) = open(&parse_key(key)?, blob)?;
+    container::check_context(header.as_ref(), &expected, allow_unbound)?;
+    Ok(PyBytes::new_bound(py, &pt).unbind())
 }
 
+/// 把旧版内置密钥加密的密文（含无头格式）用正式密钥重新加密，保留原有的关联数据上下文
+#[cfg(feature = "legacy-default-key")]
 #[pyfunction]
-fn decrypt_bytes(py: Python<'_>, key: &str, blob: &[u8]) -> PyResult<Py<PyBytes>> {
-    if blob.len() < 12 {
-        return Err(PyValueError::new_err("密文格式错误，长度不足"));
-    }
-    let key_bytes = parse_key(key)?;
-    let cipher =
-        Aes256Gcm::new_from_slice(&key_bytes).map_err(|e| PyValueError::new_err(e.to_string()))?;
-    let (nonce_bytes, ct) = blob.split_at(12);
-    let nonce = Nonce::from_slice(nonce_bytes);
-    let pt = cipher
-        .decrypt(nonce, ct)
-        .map_err(|e| PyValueError::new_err(e.to_string()))?;
-    Ok(PyBytes::new(py, &pt).into())
+#[pyo3(signature = (blob, new_key, *, key_id = None))]
+fn migrate_default_key_blob(
+    py: Python<'_>,
+    blob: &[u8],
+    new_key: &str,
+    key_id: Option<&str>,
+) -> PyResult<Py<PyBytes>> {
+    let out = rotate::reencrypt_blob(
+        &key::default_key(),
+        &parse_key(new_key)?,
+        blob,
+        key_id,
+        None,
+        None,
+    )?;
+    Ok(PyBytes::new_bound(py, &out).unbind())
 }
 
 #[pymodule]
-fn strategy_crypto(_py: Python, m: &PyModule) -> PyResult<()> {
+fn strategy_crypto(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add("FORMAT_VERSION", FORMAT_VERSION)?;
+    m.add("ALGORITHMS", Algorithm::ALL.map(|alg| alg.name()).to_vec())?;
+    error::register(m)?;
+    m.add_class::<keyring::Keyring>()?;
+    m.add_class::<stream::EncryptWriter>()?;
+    m.add_class::<stream::DecryptReader>()?;
+    m.add_class::<bundle::Bundle>()?;
+    m.add_class::<importer::BundleImporter>()?;
+    m.add_class::<license::License>()?;
+    m.add_class::<grace::OfflineGrace>()?;
+    m.add_function(wrap_pyfunction!(key::set_strict_keys, m)?)?;
+    m.add_function(wrap_pyfunction!(secret::set_mlock, m)?)?;
+    m.add_function(wrap_pyfunction!(compress::set_max_decompressed_size, m)?)?;
+    m.add_function(wrap_pyfunction!(sign::set_trusted_publishers, m)?)?;
+    m.add_function(wrap_pyfunction!(sign::trusted_publishers, m)?)?;
+    m.add_function(wrap_pyfunction!(sign::generate_publisher_key, m)?)?;
+    m.add_function(wrap_pyfunction!(sign::publisher_public_key, m)?)?;
+    m.add_function(wrap_pyfunction!(license::set_license_authorities, m)?)?;
+    m.add_function(wrap_pyfunction!(license::license_authorities, m)?)?;
+    m.add_function(wrap_pyfunction!(license::issue_license, m)?)?;
+    m.add_function(wrap_pyfunction!(license::machine_id, m)?)?;
+    m.add_function(wrap_pyfunction!(grace::time_challenge, m)?)?;
+    m.add_function(wrap_pyfunction!(grace::issue_time_token, m)?)?;
+    m.add_function(wrap_pyfunction!(wrap::generate_device_key, m)?)?;
+    m.add_function(wrap_pyfunction!(wrap::device_public_key, m)?)?;
+    m.add_function(wrap_pyfunction!(wrap::set_device_key, m)?)?;
+    m.add_function(wrap_pyfunction!(wrap::wrap_key, m)?)?;
+    m.add_function(wrap_pyfunction!(revocation::issue_revocation_list, m)?)?;
+    m.add_function(wrap_pyfunction!(revocation::set_revocation_list, m)?)?;
+    m.add_function(wrap_pyfunction!(
+        revocation::load_cached_revocation_list,
+        m
+    )?)?;
+    m.add_function(wrap_pyfunction!(revocation::revocation_list, m)?)?;
+    m.add_function(wrap_pyfunction!(container::is_encrypted, m)?)?;
+    m.add_function(wrap_pyfunction!(container::inspect, m)?)?;
     m.add_function(wrap_pyfunction!(encrypt_bytes, m)?)?;
     m.add_function(wrap_pyfunction!(decrypt_bytes, m)?)?;
+    m.add_function(wrap_pyfunction!(encrypt_bytes_with_context, m)?)?;
+    m.add_function(wrap_pyfunction!(decrypt_bytes_with_context, m)?)?;
+    m.add_function(wrap_pyfunction!(license::decrypt_licensed, m)?)?;
+    m.add_function(wrap_pyfunction!(kdf::encrypt_with_password, m)?)?;
+    m.add_function(wrap_pyfunction!(kdf::decrypt_with_password, m)?)?;
+    m.add_function(wrap_pyfunction!(kdf::derive_key, m)?)?;
+    m.add_function(wrap_pyfunction!(kdf::encrypt_bytes_derived, m)?)?;
+    m.add_function(wrap_pyfunction!(kdf::decrypt_bytes_derived, m)?)?;
+    m.add_function(wrap_pyfunction!(rotate::reencrypt, m)?)?;
+    m.add_function(wrap_pyfunction!(rotate::reencrypt_dir, m)?)?;
+    m.add_function(wrap_pyfunction!(bundle::pack_bundle, m)?)?;
+    m.add_function(wrap_pyfunction!(bundle::is_bundle, m)?)?;
+    #[cfg(feature = "legacy-default-key")]
+    m.add_function(wrap_pyfunction!(migrate_default_key_blob, m)?)?;
     Ok(())
 }
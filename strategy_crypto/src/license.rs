@@ -0,0 +1,494 @@
+//! 授权凭证
+//!
+//! 授权由服务端用 Ed25519 私钥签发，配置受信任的授权公钥后在解密前校验：
+//! 授权对象、机器绑定、策略 ID 列表、允许的运行模式以及生效/过期时间。
+//! 拿到策略密钥的人若没有覆盖该策略和模式的有效授权，`decrypt_licensed` 不会解密。
+//! 配置授权公钥后，不带授权的解密接口（`decrypt_bytes`、`Keyring.decrypt` 等）一律拒绝；
+//! `Bundle` 与 `DecryptReader` 通过 `license` / `mode` 参数提供授权，策略 ID 取密文绑定的关联数据。
+//!
+//! 凭证为文本 `btl1.<payload>.<signature>`（URL 安全 base64，无填充），payload 布局（小端）：
+//!
+//! ```text
+//! version(1) | not_before(8) | expires_at(8) | modes(1) | licensee | machine | count(2) | strategy_id * count
+//! ```
+//!
+//! 字符串均为 `len(2) | UTF-8`，machine 为空表示不绑定机器。
+
+use std::process::Command;
+use std::sync::RwLock;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use ed25519_dalek::{Signature, Signer, SigningKey, VerifyingKey};
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::PyBytes;
+use sha2::{Digest, Sha256};
+
+use crate::cipher::open;
+use crate::container::{self, Context, Reader, PUBLIC_KEY_LEN};
+use crate::error::LicenseError;
+use crate::grace::OfflineGrace;
+use crate::key::parse_key;
+use crate::revocation;
+use crate::sign::{encode_public_key, parse_public_key, parse_signing_key};
+
+const TOKEN_PREFIX: &str = "btl1.";
+const LICENSE_VERSION: u8 = 1;
+
+/// 签名消息的域分隔前缀
+const LICENSE_LABEL: &[u8] = b"bullet-trade/license/v1";
+/// 机器标识的哈希前缀，避免直接暴露系统的原始机器 ID
+const MACHINE_LABEL: &[u8] = b"bullet-trade/machine/v1";
+
+/// 受信任的授权签发公钥
+static AUTHORITIES: RwLock<Vec<[u8; PUBLIC_KEY_LEN]>> = RwLock::new(Vec::new());
+
+/// 运行模式，按位记录在授权中
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    Backtest,
+    Paper,
+    Live,
+}
+
+impl Mode {
+    const ALL: [Mode; 3] = [Mode::Backtest, Mode::Paper, Mode::Live];
+
+    fn bit(self) -> u8 {
+        match self {
+            Mode::Backtest => 0x01,
+            Mode::Paper => 0x02,
+            Mode::Live => 0x04,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Mode::Backtest => "backtest",
+            Mode::Paper => "paper",
+            Mode::Live => "live",
+        }
+    }
+
+    fn from_name(name: &str) -> PyResult<Self> {
+        Mode::ALL
+            .into_iter()
+            .find(|mode| mode.name().eq_ignore_ascii_case(name))
+            .ok_or_else(|| {
+                PyValueError::new_err(format!(
+                    "不支持的运行模式: {name}，可选 backtest / paper / live"
+                ))
+            })
+    }
+}
+
+pub fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs() as i64)
+}
+
+/// 有离线宽限期状态时以其可信时间为准，回拨时钟无法让过期授权重新生效
+fn trusted_now(now: i64, grace: Option<&OfflineGrace>) -> PyResult<i64> {
+    grace.map_or(Ok(now), |grace| grace.enforce(now).map(|(now, _)| now))
+}
+
+pub fn push_str(out: &mut Vec<u8>, s: &str) -> PyResult<()> {
+    let len = u16::try_from(s.len()).map_err(|_| PyValueError::new_err("凭证字段过长"))?;
+    out.extend_from_slice(&len.to_le_bytes());
+    out.extend_from_slice(s.as_bytes());
+    Ok(())
+}
+
+/// 签名凭证 payload 的读取游标
+pub fn token_reader(raw: &[u8]) -> Reader<'_> {
+    Reader::new(raw, "凭证内容", LicenseError::new_err)
+}
+
+/// 生成 `<prefix><payload>.<signature>` 形式的签名凭证（URL 安全 base64，无填充）
+pub fn sign_token(signing_key: &SigningKey, prefix: &str, label: &[u8], payload: &[u8]) -> String {
+    let signature = signing_key.sign(&[label, payload].concat());
+    format!(
+        "{prefix}{}.{}",
+        URL_SAFE_NO_PAD.encode(payload),
+        URL_SAFE_NO_PAD.encode(signature.to_bytes())
+    )
+}
+
+/// 拆分签名凭证并用受信任的授权公钥校验签名，返回 payload；授权与吊销列表共用这组公钥
+pub fn open_token(token: &str, prefix: &str, label: &[u8]) -> PyResult<Vec<u8>> {
+    let (payload, signature) = token
+        .trim()
+        .strip_prefix(prefix)
+        .and_then(|rest| rest.split_once('.'))
+        .ok_or_else(|| LicenseError::new_err("不是有效的签名凭证"))?;
+    let (Ok(payload), Ok(signature)) = (
+        URL_SAFE_NO_PAD.decode(payload),
+        URL_SAFE_NO_PAD.decode(signature),
+    ) else {
+        return Err(LicenseError::new_err("凭证编码错误"));
+    };
+    let signature =
+        Signature::from_slice(&signature).map_err(|_| LicenseError::new_err("凭证签名格式错误"))?;
+    let authorities = AUTHORITIES
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone();
+    if authorities.is_empty() {
+        return Err(LicenseError::new_err(
+            "未配置授权公钥，请先调用 set_license_authorities",
+        ));
+    }
+    let message = [label, &payload].concat();
+    let trusted = authorities.iter().any(|key| {
+        VerifyingKey::from_bytes(key)
+            .is_ok_and(|key| key.verify_strict(&message, &signature).is_ok())
+    });
+    if !trusted {
+        return Err(LicenseError::new_err(
+            "凭证签名校验失败：凭证被篡改或不是受信任的服务端签发",
+        ));
+    }
+    Ok(payload)
+}
+
+/// 原始机器 ID：Linux 为 machine-id，Windows 为 MachineGuid，macOS 为 IOPlatformUUID
+pub fn raw_machine_id() -> PyResult<String> {
+    let id = if cfg!(target_os = "windows") {
+        command_output(
+            "reg",
+            &[
+                "query",
+                r"HKLM\SOFTWARE\Microsoft\Cryptography",
+                "/v",
+                "MachineGuid",
+            ],
+        )
+        .and_then(|out| {
+            out.lines()
+                .find(|line| line.contains("MachineGuid"))
+                .and_then(|line| line.split_whitespace().last())
+                .map(str::to_string)
+        })
+    } else if cfg!(target_os = "macos") {
+        command_output("ioreg", &["-rd1", "-c", "IOPlatformExpertDevice"]).and_then(|out| {
+            out.lines()
+                .find(|line| line.contains("IOPlatformUUID"))
+                .and_then(|line| line.split('"').nth(3))
+                .map(str::to_string)
+        })
+    } else {
+        ["/etc/machine-id", "/var/lib/dbus/machine-id"]
+            .iter()
+            .find_map(|path| std::fs::read_to_string(path).ok())
+            .map(|id| id.trim().to_string())
+    };
+    id.filter(|id| !id.is_empty())
+        .ok_or_else(|| LicenseError::new_err("无法读取本机机器标识"))
+}
+
+fn command_output(program: &str, args: &[&str]) -> Option<String> {
+    let out = Command::new(program).args(args).output().ok()?;
+    out.status
+        .success()
+        .then(|| String::from_utf8_lossy(&out.stdout).into_owned())
+}
+
+fn authorities_configured() -> bool {
+    !AUTHORITIES
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .is_empty()
+}
+
+/// 已配置授权公钥时拒绝不带授权的解密
+pub fn check_unlicensed_allowed() -> PyResult<()> {
+    if authorities_configured() {
+        Err(LicenseError::new_err(
+            "已配置授权公钥，解密需要授权：请使用 decrypt_licensed 或传入 license",
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+/// `Bundle` / `DecryptReader` 的授权检查：未提供授权时按 `check_unlicensed_allowed` 处理；
+/// 提供授权时要求密文绑定了 strategy_id，且授权覆盖该策略与 `mode`
+pub fn check_bound_license(
+    license: Option<&License>,
+    mode: Option<&str>,
+    grace: Option<&OfflineGrace>,
+    context: Option<&Context>,
+) -> PyResult<()> {
+    let Some(license) = license else {
+        return check_unlicensed_allowed();
+    };
+    let mode = mode.ok_or_else(|| PyValueError::new_err("提供 license 时必须指定 mode"))?;
+    let strategy_id = context
+        .and_then(|context| context.get("strategy_id"))
+        .ok_or_else(|| LicenseError::new_err("密文未绑定 strategy_id，无法校验授权"))?;
+    let now = trusted_now(now(), grace)?;
+    license.check_at(strategy_id, Mode::from_name(mode)?, now)
+}
+
+/// 本机的机器标识：原始机器 ID 加前缀后的 SHA-256 前 16 字节（hex）
+#[pyfunction]
+pub fn machine_id() -> PyResult<String> {
+    let digest = Sha256::new()
+        .chain_update(MACHINE_LABEL)
+        .chain_update(raw_machine_id()?.as_bytes())
+        .finalize();
+    Ok(hex::encode(&digest[..16]))
+}
+
+/// 已验证签名的授权
+#[pyclass(module = "strategy_crypto")]
+#[derive(Debug, Clone)]
+pub struct License {
+    /// payload 的 SHA-256 前 16 字节（hex），吊销列表据此吊销单个授权
+    license_id: String,
+    licensee: String,
+    machine: Option<String>,
+    strategy_ids: Vec<String>,
+    modes: u8,
+    not_before: i64,
+    expires_at: i64,
+}
+
+impl License {
+    fn encode(&self) -> PyResult<Vec<u8>> {
+        let mut out = vec![LICENSE_VERSION];
+        out.extend_from_slice(&self.not_before.to_le_bytes());
+        out.extend_from_slice(&self.expires_at.to_le_bytes());
+        out.push(self.modes);
+        push_str(&mut out, &self.licensee)?;
+        push_str(&mut out, self.machine.as_deref().unwrap_or_default())?;
+        let count = u16::try_from(self.strategy_ids.len())
+            .map_err(|_| PyValueError::new_err("授权包含的策略过多"))?;
+        out.extend_from_slice(&count.to_le_bytes());
+        for id in &self.strategy_ids {
+            push_str(&mut out, id)?;
+        }
+        Ok(out)
+    }
+
+    fn decode(raw: &[u8]) -> PyResult<Self> {
+        let mut reader = token_reader(raw);
+        let [version] = reader.array()?;
+        if version != LICENSE_VERSION {
+            return Err(LicenseError::new_err(format!(
+                "不支持的授权版本: {version}"
+            )));
+        }
+        let not_before = i64::from_le_bytes(reader.array()?);
+        let expires_at = i64::from_le_bytes(reader.array()?);
+        let [modes] = reader.array()?;
+        let licensee = reader.string()?;
+        let machine = Some(reader.string()?).filter(|m| !m.is_empty());
+        let count = u16::from_le_bytes(reader.array()?);
+        let strategy_ids = (0..count)
+            .map(|_| reader.string())
+            .collect::<PyResult<_>>()?;
+        Ok(License {
+            license_id: hex::encode(&Sha256::digest(raw)[..16]),
+            licensee,
+            machine,
+            strategy_ids,
+            modes,
+            not_before,
+            expires_at,
+        })
+    }
+
+    /// 解析凭证并用受信任的授权公钥校验签名
+    pub fn verify(token: &str) -> PyResult<Self> {
+        License::decode(&open_token(token, TOKEN_PREFIX, LICENSE_LABEL)?)
+    }
+
+    /// 检查授权是否覆盖指定策略与模式，`now` 为 Unix 秒
+    pub fn check_at(&self, strategy_id: &str, mode: Mode, now: i64) -> PyResult<()> {
+        revocation::check_license(&self.license_id, strategy_id)?;
+        if now < self.not_before {
+            return Err(LicenseError::new_err("授权尚未生效"));
+        }
+        if now >= self.expires_at {
+            return Err(LicenseError::new_err("授权已过期"));
+        }
+        if let Some(machine) = &self.machine {
+            if *machine != machine_id()? {
+                return Err(LicenseError::new_err("授权未绑定到本机"));
+            }
+        }
+        if !self.strategy_ids.iter().any(|id| id == strategy_id) {
+            return Err(LicenseError::new_err(format!(
+                "授权不包含策略 {strategy_id}"
+            )));
+        }
+        if self.modes & mode.bit() == 0 {
+            return Err(LicenseError::new_err(format!(
+                "授权不允许 {} 模式",
+                mode.name()
+            )));
+        }
+        Ok(())
+    }
+}
+
+#[pymethods]
+impl License {
+    /// 解析并校验授权凭证；支持 `file:<路径>` 从文件读取
+    #[new]
+    fn new(token: &str) -> PyResult<Self> {
+        match token.strip_prefix("file:") {
+            Some(path) => License::verify(&std::fs::read_to_string(path)?),
+            None => License::verify(token),
+        }
+    }
+
+    #[getter]
+    fn license_id(&self) -> &str {
+        &self.license_id
+    }
+
+    #[getter]
+    fn licensee(&self) -> &str {
+        &self.licensee
+    }
+
+    #[getter]
+    fn machine(&self) -> Option<&str> {
+        self.machine.as_deref()
+    }
+
+    #[getter]
+    fn strategy_ids(&self) -> Vec<String> {
+        self.strategy_ids.clone()
+    }
+
+    #[getter]
+    fn modes(&self) -> Vec<&'static str> {
+        Mode::ALL
+            .into_iter()
+            .filter(|mode| self.modes & mode.bit() != 0)
+            .map(Mode::name)
+            .collect()
+    }
+
+    #[getter]
+    fn not_before(&self) -> i64 {
+        self.not_before
+    }
+
+    #[getter]
+    fn expires_at(&self) -> i64 {
+        self.expires_at
+    }
+
+    /// 不覆盖时抛出 LicenseError；`now` 省略时取当前时间。
+    /// 传入 `grace` 时先检查离线宽限期与时钟回拨，并以其可信时间判断是否过期
+    #[pyo3(signature = (strategy_id, mode, *, now = None, grace = None))]
+    fn check(
+        &self,
+        strategy_id: &str,
+        mode: &str,
+        now: Option<i64>,
+        grace: Option<PyRef<'_, OfflineGrace>>,
+    ) -> PyResult<()> {
+        let now = trusted_now(now.unwrap_or_else(self::now), grace.as_deref())?;
+        self.check_at(strategy_id, Mode::from_name(mode)?, now)
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "License(license_id={:?}, licensee={:?}, strategy_ids={:?}, modes={:?}, expires_at={})",
+            self.license_id,
+            self.licensee,
+            self.strategy_ids,
+            self.modes(),
+            self.expires_at
+        )
+    }
+}
+
+/// 签发授权凭证（服务端使用）。`machine` 为 `machine_id()` 的结果，省略时不绑定机器；
+/// `not_before` 省略时为当前时间，时间均为 Unix 秒。
+#[pyfunction]
+#[pyo3(signature = (signing_key, licensee, strategy_ids, modes, expires_at, *, machine = None, not_before = None))]
+pub fn issue_license(
+    signing_key: &str,
+    licensee: &str,
+    strategy_ids: Vec<String>,
+    modes: Vec<String>,
+    expires_at: i64,
+    machine: Option<String>,
+    not_before: Option<i64>,
+) -> PyResult<String> {
+    let signing_key = parse_signing_key(signing_key)?;
+    let modes = modes
+        .iter()
+        .map(|name| Mode::from_name(name).map(Mode::bit))
+        .try_fold(0u8, |acc, bit| bit.map(|bit| acc | bit))?;
+    let license = License {
+        // 授权 ID 由签发后的 payload 哈希得到，不参与编码
+        license_id: String::new(),
+        licensee: licensee.to_string(),
+        machine: machine.filter(|m| !m.is_empty()),
+        strategy_ids,
+        modes,
+        not_before: not_before.unwrap_or_else(now),
+        expires_at,
+    };
+    Ok(sign_token(
+        &signing_key,
+        TOKEN_PREFIX,
+        LICENSE_LABEL,
+        &license.encode()?,
+    ))
+}
+
+/// 设置受信任的授权签发公钥（替换原有列表）；非空时不带授权的解密接口一律拒绝
+#[pyfunction]
+pub fn set_license_authorities(keys: Vec<String>) -> PyResult<()> {
+    let keys = keys
+        .iter()
+        .map(|spec| parse_public_key(spec).map(|key| key.to_bytes()))
+        .collect::<PyResult<Vec<_>>>()?;
+    *AUTHORITIES.write().unwrap_or_else(|e| e.into_inner()) = keys;
+    Ok(())
+}
+
+/// 当前受信任的授权签发公钥
+#[pyfunction]
+pub fn license_authorities() -> Vec<String> {
+    AUTHORITIES
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .iter()
+        .map(encode_public_key)
+        .collect()
+}
+
+/// 先校验授权覆盖 `strategy_id` 与 `mode`，再解密并要求密文绑定同一 `strategy_id`。
+///
+/// `allow_unbound=True` 时接受旧版无头格式密文（授权检查仍然生效）；
+/// 传入 `grace` 时同时执行离线宽限期检查。
+#[pyfunction]
+#[pyo3(signature = (key, blob, license, strategy_id, mode, *, allow_unbound = false, grace = None))]
+pub fn decrypt_licensed(
+    py: Python<'_>,
+    key: &str,
+    blob: &[u8],
+    license: PyRef<'_, License>,
+    strategy_id: &str,
+    mode: &str,
+    allow_unbound: bool,
+    grace: Option<PyRef<'_, OfflineGrace>>,
+) -> PyResult<Py<PyBytes>> {
+    let now = trusted_now(now(), grace.as_deref())?;
+    license.check_at(strategy_id, Mode::from_name(mode)?, now)?;
+    let (header, pt) = open(&parse_key(key)?, blob)?;
+    let expected = Context::from([("strategy_id".to_string(), strategy_id.to_string())]);
+    container::check_context(header.as_ref(), &expected, allow_unbound)?;
+    Ok(PyBytes::new_bound(py, &pt).unbind())
+}
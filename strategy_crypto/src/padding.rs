@@ -0,0 +1,119 @@
+//! 隐藏明文长度的填充
+//!
+//! 加密前把明文（或压缩后的数据）按策略补齐到固定档位，填充方式为 ISO/IEC 7816-4：
+//! 末尾追加一个 0x80 再补 0x00，解密后去掉即可还原；填充位于 AEAD 内部，随密文一起认证。
+//! 填充策略写入头部，密文长度只暴露明文所在的档位。
+//!
+//! - `pow2`：补齐到 2 的幂，开销最多一倍，适合长度差异很大的策略源码
+//! - `block:<n>`：补齐到 n 字节的整数倍，开销最多 n 字节
+
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+
+use crate::error::{MalformedCiphertextError, UnsupportedVersionError};
+use crate::secret::SecretBytes;
+
+pub const MAX_BLOCK_SIZE: u32 = 16 * 1024 * 1024;
+
+const POLICY_POW2: u8 = 1;
+const POLICY_BLOCK: u8 = 2;
+
+/// policy(1) | block_size(4)
+const ENCODED_LEN: usize = 1 + 4;
+
+/// 填充结束标记
+const MARKER: u8 = 0x80;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Padding {
+    PowerOfTwo,
+    Block(u32),
+}
+
+impl Padding {
+    /// 解析 Python 传入的填充策略，`None` 表示不填充
+    pub fn from_spec(spec: Option<&str>) -> PyResult<Option<Self>> {
+        let Some(spec) = spec else {
+            return Ok(None);
+        };
+        if spec.eq_ignore_ascii_case("pow2") {
+            return Ok(Some(Padding::PowerOfTwo));
+        }
+        let block = spec
+            .strip_prefix("block:")
+            .and_then(|n| n.trim().parse::<u32>().ok())
+            .filter(|n| (1..=MAX_BLOCK_SIZE).contains(n))
+            .ok_or_else(|| {
+                PyValueError::new_err(format!(
+                    "不支持的填充策略: {spec}，可选 pow2 或 block:<1..={MAX_BLOCK_SIZE}>"
+                ))
+            })?;
+        Ok(Some(Padding::Block(block)))
+    }
+
+    pub fn spec(self) -> String {
+        match self {
+            Padding::PowerOfTwo => "pow2".to_string(),
+            Padding::Block(n) => format!("block:{n}"),
+        }
+    }
+
+    pub fn encode(self) -> Vec<u8> {
+        let (policy, block) = match self {
+            Padding::PowerOfTwo => (POLICY_POW2, 0),
+            Padding::Block(n) => (POLICY_BLOCK, n),
+        };
+        let mut out = Vec::with_capacity(ENCODED_LEN);
+        out.push(policy);
+        out.extend_from_slice(&block.to_le_bytes());
+        out
+    }
+
+    pub fn decode(raw: &[u8]) -> PyResult<Self> {
+        if raw.len() != ENCODED_LEN {
+            return Err(MalformedCiphertextError::new_err("填充参数长度错误"));
+        }
+        let block = u32::from_le_bytes([raw[1], raw[2], raw[3], raw[4]]);
+        match raw[0] {
+            POLICY_POW2 => Ok(Padding::PowerOfTwo),
+            POLICY_BLOCK if (1..=MAX_BLOCK_SIZE).contains(&block) => Ok(Padding::Block(block)),
+            POLICY_BLOCK => Err(MalformedCiphertextError::new_err(format!(
+                "填充块大小超出范围: {block}"
+            ))),
+            other => Err(UnsupportedVersionError::new_err(format!(
+                "不支持的填充策略: {other}"
+            ))),
+        }
+    }
+
+    /// 长度为 `len` 的数据填充后的长度（至少多出一个结束标记）
+    fn padded_len(self, len: usize) -> PyResult<usize> {
+        let min = len + 1;
+        let padded = match self {
+            Padding::PowerOfTwo => min.checked_next_power_of_two(),
+            Padding::Block(n) => min.div_ceil(n as usize).checked_mul(n as usize),
+        };
+        padded.ok_or_else(|| PyValueError::new_err("数据过大，无法填充"))
+    }
+}
+
+pub fn pad(padding: Padding, data: &[u8]) -> PyResult<SecretBytes> {
+    let len = padding.padded_len(data.len())?;
+    let mut out = SecretBytes::with_capacity(len);
+    let buf = out.as_vec_mut();
+    buf.extend_from_slice(data);
+    buf.push(MARKER);
+    buf.resize(len, 0);
+    Ok(out)
+}
+
+/// 去掉填充；数据已经过认证，这里只会在加密端实现有误时失败
+pub fn unpad(mut data: SecretBytes) -> PyResult<SecretBytes> {
+    let end = data
+        .iter()
+        .rposition(|&b| b != 0)
+        .filter(|&i| data[i] == MARKER)
+        .ok_or_else(|| MalformedCiphertextError::new_err("填充格式错误"))?;
+    data.as_vec_mut().truncate(end);
+    Ok(data)
+}
@@ -0,0 +1,252 @@
+//! 吊销列表
+//!
+//! 下架有问题的策略版本或吊销成员后，已经下载到本地的密文和授权仍然可用。服务端用授权私钥
+//! 签发吊销列表，列出被吊销的密钥 ID、策略（版本）与授权 ID；加载后每次解密都会检查，
+//! 命中即拒绝。列表带单调递增的序号，比已见过的列表旧时拒绝接受，防止重放旧列表解除吊销。
+//!
+//! 凭证为文本 `btr1.<payload>.<signature>`，与授权凭证使用同一组授权公钥，payload 布局（小端）：
+//!
+//! ```text
+//! version(1) | sequence(8) | issued_at(8) | key_ids | strategies | license_ids
+//! ```
+//!
+//! 每个列表为 `count(2) | string * count`，字符串为 `len(2) | UTF-8`。
+//! 策略条目为 `<strategy_id>`（吊销全部版本）或 `<strategy_id>@<version>`，
+//! 与密文上下文中的 `strategy_id` / `version` 比对。
+//!
+//! 接受的列表会原样缓存到本地文件，离线启动时从缓存恢复；缓存被删除只会丢失回退保护的下限，
+//! 缓存本身带签名，无法篡改。
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+
+use crate::container::{Header, Reader};
+use crate::error::RevocationError;
+use crate::files;
+use crate::license::{now, open_token, push_str, sign_token, token_reader};
+use crate::sign::parse_signing_key;
+
+const TOKEN_PREFIX: &str = "btr1.";
+const REVOCATION_VERSION: u8 = 1;
+
+/// 签名消息的域分隔前缀
+const REVOCATION_LABEL: &[u8] = b"bullet-trade/revocation/v1";
+
+/// 当前生效的吊销列表
+static CURRENT: RwLock<Option<RevocationList>> = RwLock::new(None);
+
+#[derive(Debug, Clone)]
+struct RevocationList {
+    sequence: u64,
+    issued_at: i64,
+    key_ids: Vec<String>,
+    strategies: Vec<String>,
+    license_ids: Vec<String>,
+}
+
+fn push_list(out: &mut Vec<u8>, items: &[String]) -> PyResult<()> {
+    let count = u16::try_from(items.len()).map_err(|_| PyValueError::new_err("吊销条目过多"))?;
+    out.extend_from_slice(&count.to_le_bytes());
+    for item in items {
+        push_str(out, item)?;
+    }
+    Ok(())
+}
+
+fn read_list(reader: &mut Reader<'_>) -> PyResult<Vec<String>> {
+    let count = u16::from_le_bytes(reader.array()?);
+    (0..count).map(|_| reader.string()).collect()
+}
+
+impl RevocationList {
+    fn encode(&self) -> PyResult<Vec<u8>> {
+        let mut out = vec![REVOCATION_VERSION];
+        out.extend_from_slice(&self.sequence.to_le_bytes());
+        out.extend_from_slice(&self.issued_at.to_le_bytes());
+        push_list(&mut out, &self.key_ids)?;
+        push_list(&mut out, &self.strategies)?;
+        push_list(&mut out, &self.license_ids)?;
+        Ok(out)
+    }
+
+    fn decode(raw: &[u8]) -> PyResult<Self> {
+        let mut reader = token_reader(raw);
+        let [version] = reader.array()?;
+        if version != REVOCATION_VERSION {
+            return Err(RevocationError::new_err(format!(
+                "不支持的吊销列表版本: {version}"
+            )));
+        }
+        Ok(RevocationList {
+            sequence: u64::from_le_bytes(reader.array()?),
+            issued_at: i64::from_le_bytes(reader.array()?),
+            key_ids: read_list(&mut reader)?,
+            strategies: read_list(&mut reader)?,
+            license_ids: read_list(&mut reader)?,
+        })
+    }
+
+    fn verify(token: &str) -> PyResult<Self> {
+        RevocationList::decode(&open_token(token, TOKEN_PREFIX, REVOCATION_LABEL)?)
+    }
+
+    fn strategy_revoked(&self, strategy_id: &str, version: Option<&str>) -> bool {
+        self.strategies
+            .iter()
+            .any(|entry| match entry.split_once('@') {
+                Some((id, v)) => id == strategy_id && Some(v) == version,
+                None => entry == strategy_id,
+            })
+    }
+}
+
+fn current() -> Option<RevocationList> {
+    CURRENT.read().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+fn read_cache(path: &Path) -> PyResult<Option<RevocationList>> {
+    match fs::read_to_string(path) {
+        Ok(token) => RevocationList::verify(&token).map(Some),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// 检查密文头部记录的密钥 ID 与上下文中的策略（版本）是否已被吊销
+pub fn check_header(header: &Header) -> PyResult<()> {
+    let Some(list) = current() else {
+        return Ok(());
+    };
+    if !header.key_id.is_empty() && list.key_ids.contains(&header.key_id) {
+        return Err(RevocationError::new_err(format!(
+            "密钥 {} 已被吊销",
+            header.key_id
+        )));
+    }
+    if let Some(context) = &header.context {
+        if let Some(strategy_id) = context.get("strategy_id") {
+            let version = context.get("version").map(String::as_str);
+            if list.strategy_revoked(strategy_id, version) {
+                return Err(RevocationError::new_err(match version {
+                    Some(version) => format!("策略 {strategy_id} 的版本 {version} 已被吊销"),
+                    None => format!("策略 {strategy_id} 已被吊销"),
+                }));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// 检查授权本身或授权所用的策略是否已被吊销
+pub fn check_license(license_id: &str, strategy_id: &str) -> PyResult<()> {
+    let Some(list) = current() else {
+        return Ok(());
+    };
+    if list.license_ids.iter().any(|id| id == license_id) {
+        return Err(RevocationError::new_err(format!(
+            "授权 {license_id} 已被吊销"
+        )));
+    }
+    if list.strategy_revoked(strategy_id, None) {
+        return Err(RevocationError::new_err(format!(
+            "策略 {strategy_id} 已被吊销"
+        )));
+    }
+    Ok(())
+}
+
+/// 签发吊销列表（服务端使用）。`sequence` 须大于此前签发的列表，`issued_at` 省略时为当前时间
+#[pyfunction]
+#[pyo3(signature = (signing_key, sequence, *, key_ids = vec![], strategies = vec![], license_ids = vec![], issued_at = None))]
+pub fn issue_revocation_list(
+    signing_key: &str,
+    sequence: u64,
+    key_ids: Vec<String>,
+    strategies: Vec<String>,
+    license_ids: Vec<String>,
+    issued_at: Option<i64>,
+) -> PyResult<String> {
+    let signing_key = parse_signing_key(signing_key)?;
+    let list = RevocationList {
+        sequence,
+        issued_at: issued_at.unwrap_or_else(now),
+        key_ids,
+        strategies,
+        license_ids,
+    };
+    Ok(sign_token(
+        &signing_key,
+        TOKEN_PREFIX,
+        REVOCATION_LABEL,
+        &list.encode()?,
+    ))
+}
+
+/// 校验并启用吊销列表，返回其序号。
+///
+/// 序号小于当前已启用的列表或 `cache_path` 中缓存的列表时抛出 RevocationError；
+/// 接受后写入 `cache_path`。缓存校验失败（例如授权公钥已轮换）时视为没有缓存。
+#[pyfunction]
+#[pyo3(signature = (token, *, cache_path = None))]
+pub fn set_revocation_list(token: &str, cache_path: Option<PathBuf>) -> PyResult<u64> {
+    let list = RevocationList::verify(token)?;
+    let cached = match &cache_path {
+        Some(path) => read_cache(path).ok().flatten(),
+        None => None,
+    };
+    let mut guard = CURRENT.write().unwrap_or_else(|e| e.into_inner());
+    let seen = guard
+        .iter()
+        .chain(cached.iter())
+        .map(|list| list.sequence)
+        .max();
+    if let Some(seen) = seen.filter(|&seen| list.sequence < seen) {
+        return Err(RevocationError::new_err(format!(
+            "吊销列表序号 {} 早于已见过的 {seen}，拒绝回退",
+            list.sequence
+        )));
+    }
+    if let Some(path) = &cache_path {
+        files::write_atomic(path, token.trim().as_bytes())?;
+    }
+    let sequence = list.sequence;
+    *guard = Some(list);
+    Ok(sequence)
+}
+
+/// 从缓存文件恢复吊销列表（离线启动时使用），返回启用后的序号；没有缓存时返回当前序号。
+/// 缓存比当前已启用的列表旧时保留当前列表。
+#[pyfunction]
+pub fn load_cached_revocation_list(cache_path: PathBuf) -> PyResult<Option<u64>> {
+    let cached = read_cache(&cache_path)?;
+    let mut guard = CURRENT.write().unwrap_or_else(|e| e.into_inner());
+    if let Some(list) = cached {
+        if guard
+            .as_ref()
+            .is_none_or(|cur| list.sequence > cur.sequence)
+        {
+            *guard = Some(list);
+        }
+    }
+    Ok(guard.as_ref().map(|list| list.sequence))
+}
+
+/// 当前生效的吊销列表，未加载时为 None
+#[pyfunction]
+pub fn revocation_list(py: Python<'_>) -> PyResult<Option<Bound<'_, PyDict>>> {
+    let Some(list) = current() else {
+        return Ok(None);
+    };
+    let out = PyDict::new_bound(py);
+    out.set_item("sequence", list.sequence)?;
+    out.set_item("issued_at", list.issued_at)?;
+    out.set_item("key_ids", list.key_ids)?;
+    out.set_item("strategies", list.strategies)?;
+    out.set_item("license_ids", list.license_ids)?;
+    Ok(Some(out))
+}
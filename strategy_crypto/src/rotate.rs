@@ -0,0 +1,158 @@
+//! 密钥轮换：在扩展内部完成“解密→重新加密”，明文不会返回给 Python
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use ed25519_dalek::SigningKey;
+use pyo3::prelude::*;
+use pyo3::types::{PyBytes, PyDict};
+
+use crate::cipher::{open, seal_signed};
+use crate::container::{Algorithm, Header};
+use crate::error::SignatureError;
+use crate::files;
+use crate::key::{parse_key, Key};
+use crate::sign::parse_signing_key;
+
+/// 用旧密钥解密后以新密钥重新加密，保留原有的关联数据上下文、压缩与填充方式；中间明文保存在安全缓冲区中，返回前清零。
+///
+/// `alg` 为 `None` 时沿用原密文的算法（旧版无头格式为 AES-256-GCM）。
+/// 原密文带发布者签名时必须提供 `signing_key` 重新签名，避免换钥后悄悄丢掉签名。
+pub fn reencrypt_blob(
+    old_key: &Key,
+    new_key: &Key,
+    blob: &[u8],
+    key_id: Option<&str>,
+    alg: Option<Algorithm>,
+    signing_key: Option<&SigningKey>,
+) -> PyResult<Vec<u8>> {
+    let (old_header, pt) = open(old_key, blob)?;
+    if signing_key.is_none() && old_header.as_ref().is_some_and(|h| h.signer.is_some()) {
+        return Err(SignatureError::new_err(
+            "原密文带有发布者签名，重新加密时需提供 signing_key",
+        ));
+    }
+    let alg = alg.unwrap_or(
+        old_header
+            .as_ref()
+            .map_or_else(Algorithm::default, |h| h.alg),
+    );
+    let mut header = Header::new(alg, key_id)?;
+    if let Some(old_header) = old_header {
+        header.context = old_header.context;
+        header.compression = old_header.compression;
+        header.padding = old_header.padding;
+    }
+    seal_signed(new_key, &header, &pt, signing_key)
+}
+
+fn reencrypt_file(
+    old_key: &Key,
+    new_key: &Key,
+    path: &Path,
+    key_id: Option<&str>,
+    alg: Option<Algorithm>,
+    signing_key: Option<&SigningKey>,
+) -> PyResult<()> {
+    let blob = fs::read(path)?;
+    let out = reencrypt_blob(old_key, new_key, &blob, key_id, alg, signing_key)?;
+    files::write_atomic(path, &out)?;
+    Ok(())
+}
+
+/// 单个密文换钥：返回新密文，明文不经过 Python
+#[pyfunction]
+#[pyo3(signature = (blob, old_key, new_key, *, key_id = None, algorithm = None, signing_key = None))]
+pub fn reencrypt(
+    py: Python<'_>,
+    blob: &[u8],
+    old_key: &str,
+    new_key: &str,
+    key_id: Option<&str>,
+    algorithm: Option<&str>,
+    signing_key: Option<&str>,
+) -> PyResult<Py<PyBytes>> {
+    let alg = algorithm
+        .map(|name| Algorithm::from_name(Some(name)))
+        .transpose()?;
+    let signing_key = signing_key.map(parse_signing_key).transpose()?;
+    let out = reencrypt_blob(
+        &parse_key(old_key)?,
+        &parse_key(new_key)?,
+        blob,
+        key_id,
+        alg,
+        signing_key.as_ref(),
+    )?;
+    Ok(PyBytes::new_bound(py, &out).unbind())
+}
+
+/// 批量换钥目录下的密文文件（可按后缀过滤、可递归），逐个原子替换。
+///
+/// 返回 `{"succeeded": [路径...], "failed": [(路径, 错误信息)...]}`，单个文件失败不影响其他文件。
+#[pyfunction]
+#[pyo3(signature = (
+    path,
+    old_key,
+    new_key,
+    *,
+    key_id = None,
+    algorithm = None,
+    suffix = None,
+    recursive = false,
+    signing_key = None,
+))]
+pub fn reencrypt_dir<'py>(
+    py: Python<'py>,
+    path: PathBuf,
+    old_key: &str,
+    new_key: &str,
+    key_id: Option<&str>,
+    algorithm: Option<&str>,
+    suffix: Option<&str>,
+    recursive: bool,
+    signing_key: Option<&str>,
+) -> PyResult<Bound<'py, PyDict>> {
+    let alg = algorithm
+        .map(|name| Algorithm::from_name(Some(name)))
+        .transpose()?;
+    let signing_key = signing_key.map(parse_signing_key).transpose()?;
+    let old_key = parse_key(old_key)?;
+    let new_key = parse_key(new_key)?;
+    let mut files = Vec::new();
+    files::collect_files(
+        &path,
+        recursive,
+        &|name| name.ends_with(files::TMP_SUFFIX),
+        &mut files,
+    )?;
+    if let Some(suffix) = suffix {
+        files.retain(|file| file.to_string_lossy().ends_with(suffix));
+    }
+    files.sort();
+
+    let results: Vec<(PathBuf, PyResult<()>)> = py.allow_threads(|| {
+        files
+            .into_iter()
+            .map(|file| {
+                let res =
+                    reencrypt_file(&old_key, &new_key, &file, key_id, alg, signing_key.as_ref());
+                (file, res)
+            })
+            .collect()
+    });
+
+    let mut succeeded = Vec::new();
+    let mut failed = Vec::new();
+    for (file, res) in results {
+        let file = file.to_string_lossy().into_owned();
+        match res {
+            Ok(()) => succeeded.push(file),
+            Err(e) => failed.push((file, e.to_string())),
+        }
+    }
+    let report = PyDict::new_bound(py);
+    report.set_item("succeeded", succeeded)?;
+    report.set_item("failed", failed)?;
+    Ok(report)
+}
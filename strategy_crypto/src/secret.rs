@@ -0,0 +1,140 @@
+//! 密钥与明文的安全缓冲区
+//!
+//! `SecretKey` / `SecretBytes` 在释放时清零整块内存；调用 `set_mlock(True)` 后，
+//! Linux 上新分配的缓冲区还会尝试 `mlock`，避免解密后的源码被换出到交换分区。
+//! `mlock` 受 `RLIMIT_MEMLOCK` 限制，失败时静默降级为仅清零；锁定按页生效且不计数，
+//! 同页上的其他缓冲区可能随某个缓冲区释放而被提前解锁，这里只作尽力而为的保护。
+
+use std::ops::{Deref, DerefMut};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use pyo3::prelude::*;
+use zeroize::Zeroize;
+
+use crate::key::KEY_LEN;
+
+static MLOCK: AtomicBool = AtomicBool::new(false);
+
+#[cfg(target_os = "linux")]
+fn lock(ptr: *const u8, len: usize) -> bool {
+    if len == 0 || !MLOCK.load(Ordering::Relaxed) {
+        return false;
+    }
+    // SAFETY: 区间来自当前持有的有效分配，mlock 不会读写其内容
+    unsafe { libc::mlock(ptr.cast(), len) == 0 }
+}
+
+#[cfg(target_os = "linux")]
+fn unlock(ptr: *const u8, len: usize) {
+    // SAFETY: 与 lock 成对调用，区间仍属于当前分配
+    unsafe {
+        libc::munlock(ptr.cast(), len);
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn lock(_ptr: *const u8, _len: usize) -> bool {
+    false
+}
+
+#[cfg(not(target_os = "linux"))]
+fn unlock(_ptr: *const u8, _len: usize) {}
+
+/// 32 字节密钥，存放在堆上以便锁定，释放时清零
+pub struct SecretKey {
+    key: Box<[u8; KEY_LEN]>,
+    locked: bool,
+}
+
+impl SecretKey {
+    pub fn zeroed() -> Self {
+        let key = Box::new([0u8; KEY_LEN]);
+        let locked = lock(key.as_ptr(), KEY_LEN);
+        SecretKey { key, locked }
+    }
+}
+
+impl Deref for SecretKey {
+    type Target = [u8; KEY_LEN];
+
+    fn deref(&self) -> &Self::Target {
+        &self.key
+    }
+}
+
+impl DerefMut for SecretKey {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.key
+    }
+}
+
+impl Drop for SecretKey {
+    fn drop(&mut self) {
+        self.key.zeroize();
+        if self.locked {
+            unlock(self.key.as_ptr(), KEY_LEN);
+        }
+    }
+}
+
+/// 明文缓冲区：容量在创建时固定（只允许缩短），保证锁定的区间始终有效，释放时清零全部容量
+pub struct SecretBytes {
+    buf: Vec<u8>,
+    locked: bool,
+}
+
+impl SecretBytes {
+    pub fn with_capacity(capacity: usize) -> Self {
+        let buf = Vec::with_capacity(capacity);
+        let locked = lock(buf.as_ptr(), buf.capacity());
+        SecretBytes { buf, locked }
+    }
+
+    pub fn from_slice(data: &[u8]) -> Self {
+        let mut out = SecretBytes::with_capacity(data.len());
+        out.buf.extend_from_slice(data);
+        out
+    }
+
+    /// 供原地解密使用；调用方不得让缓冲区超过初始容量
+    pub fn as_vec_mut(&mut self) -> &mut Vec<u8> {
+        &mut self.buf
+    }
+
+    /// 追加数据；容量不足时换用更大的缓冲区，旧缓冲区随即清零释放，不会留下未清零的副本
+    pub fn extend_from_slice(&mut self, data: &[u8]) {
+        let needed = self.buf.len() + data.len();
+        if needed > self.buf.capacity() {
+            let mut grown = SecretBytes::with_capacity(needed.max(self.buf.capacity() * 2));
+            grown.buf.extend_from_slice(&self.buf);
+            *self = grown;
+        }
+        self.buf.extend_from_slice(data);
+    }
+}
+
+impl Deref for SecretBytes {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.buf
+    }
+}
+
+impl Drop for SecretBytes {
+    fn drop(&mut self) {
+        let (ptr, capacity) = (self.buf.as_ptr(), self.buf.capacity());
+        // Vec<u8> 的 zeroize 会连同未使用的容量一起清零
+        self.buf.zeroize();
+        if self.locked {
+            unlock(ptr, capacity);
+        }
+    }
+}
+
+/// 开启/关闭对新分配的密钥与明文缓冲区执行 mlock；返回当前平台是否支持
+#[pyfunction]
+pub fn set_mlock(enabled: bool) -> bool {
+    MLOCK.store(enabled, Ordering::Relaxed);
+    cfg!(target_os = "linux")
+}
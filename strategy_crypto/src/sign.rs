@@ -0,0 +1,185 @@
+//! 发布者签名
+//!
+//! 对称密钥会下发给每个客户端，AEAD 认证只能证明密文出自“某个持有密钥的人”。
+//! 发布者用 Ed25519 私钥对整条密文（头部、nonce、密文与 tag）签名，公钥写入头部并参与 AEAD 认证，
+//! 签名附加在末尾。分块流无法整体签名，改为每块之后附加该块的签名，签名覆盖流标识（头部与 salt
+//! 的哈希）、块序号、结束标志与该块密文，逐块校验后才输出明文，随机访问时同样只校验读到的块。
+//! 配置受信任的发布者公钥后，解密前先校验签名，未签名或签名者不受信任的密文
+//! 一律拒绝，泄露的策略密钥无法用来伪造策略下发给其他用户。
+//!
+//! 签名私钥与公钥沿用密钥字符串的前缀格式（`b64:` / `hex:` / `file:` 等），均为 32 字节。
+
+use std::sync::RwLock;
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use ed25519_dalek::{Signature, Signer, SigningKey, VerifyingKey};
+use pyo3::prelude::*;
+use rand::rngs::OsRng;
+use sha2::{Digest, Sha256};
+use zeroize::Zeroizing;
+
+use crate::container::{Header, PUBLIC_KEY_LEN, SIGNATURE_LEN};
+use crate::error::{InvalidKeyError, SignatureError};
+use crate::key::parse_key;
+
+/// 签名消息的域分隔前缀，避免签名被挪用到其他协议
+const SIGNATURE_LABEL: &[u8] = b"bullet-trade/publisher-sig/v1";
+const CHUNK_SIGNATURE_LABEL: &[u8] = b"bullet-trade/publisher-chunk-sig/v1";
+
+/// 受信任的发布者公钥；为空时不强制要求签名
+static TRUSTED: RwLock<Vec<[u8; PUBLIC_KEY_LEN]>> = RwLock::new(Vec::new());
+
+fn trusted() -> Vec<[u8; PUBLIC_KEY_LEN]> {
+    TRUSTED.read().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+pub fn encode_public_key(key: &[u8; PUBLIC_KEY_LEN]) -> String {
+    format!("b64:{}", STANDARD.encode(key))
+}
+
+pub fn parse_signing_key(spec: &str) -> PyResult<SigningKey> {
+    Ok(SigningKey::from_bytes(&*parse_key(spec)?))
+}
+
+pub fn parse_public_key(spec: &str) -> PyResult<VerifyingKey> {
+    VerifyingKey::from_bytes(&*parse_key(spec)?)
+        .map_err(|_| InvalidKeyError::new_err(format!("无效的发布者公钥: {spec}")))
+}
+
+fn message(signed: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(SIGNATURE_LABEL.len() + signed.len());
+    out.extend_from_slice(SIGNATURE_LABEL);
+    out.extend_from_slice(signed);
+    out
+}
+
+/// 在已封装的密文末尾追加签名；头部须已写入该私钥对应的公钥
+pub fn append_signature(signing_key: &SigningKey, blob: &mut Vec<u8>) {
+    let signature = signing_key.sign(&message(blob));
+    blob.extend_from_slice(&signature.to_bytes());
+}
+
+/// 头部记录的签名者公钥；配置了受信任发布者时签名者必须在列表中
+pub fn signer_key(signer: &[u8; PUBLIC_KEY_LEN]) -> PyResult<VerifyingKey> {
+    let trusted = trusted();
+    if !trusted.is_empty() && !trusted.contains(signer) {
+        return Err(SignatureError::new_err(format!(
+            "签名者 {} 不在受信任的发布者列表中",
+            encode_public_key(signer)
+        )));
+    }
+    VerifyingKey::from_bytes(signer).map_err(|_| SignatureError::new_err("发布者公钥无效"))
+}
+
+/// 校验签名并返回去掉签名后的密文；未签名的密文仅在未配置受信任发布者时放行
+pub fn verify<'a>(header: &Header, blob: &'a [u8]) -> PyResult<&'a [u8]> {
+    let Some(signer) = &header.signer else {
+        check_unsigned_allowed()?;
+        return Ok(blob);
+    };
+    let key = signer_key(signer)?;
+    let (signed, signature) = blob.split_at(blob.len() - SIGNATURE_LEN);
+    let signature = Signature::from_slice(signature)
+        .map_err(|_| SignatureError::new_err("发布者签名格式错误"))?;
+    key.verify_strict(&message(signed), &signature)
+        .map_err(|_| SignatureError::new_err("发布者签名校验失败：密文被篡改或签名伪造"))?;
+    Ok(signed)
+}
+
+/// 分块流第 `index` 块的签名消息
+fn chunk_message(stream_id: &[u8; 32], index: u32, last: bool, chunk: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(CHUNK_SIGNATURE_LABEL.len() + 32 + 5 + chunk.len());
+    out.extend_from_slice(CHUNK_SIGNATURE_LABEL);
+    out.extend_from_slice(stream_id);
+    out.extend_from_slice(&index.to_be_bytes());
+    out.push(last as u8);
+    out.extend_from_slice(chunk);
+    out
+}
+
+/// 分块流的流标识：头部与 salt 的哈希，使各块签名无法挪到其他流中
+pub fn stream_id(header: &[u8], salt: &[u8]) -> [u8; 32] {
+    Sha256::new()
+        .chain_update(header)
+        .chain_update(salt)
+        .finalize()
+        .into()
+}
+
+/// 对分块流的一块（`ciphertext||tag`）签名
+pub fn sign_chunk(
+    signing_key: &SigningKey,
+    stream_id: &[u8; 32],
+    index: u32,
+    last: bool,
+    chunk: &[u8],
+) -> [u8; SIGNATURE_LEN] {
+    signing_key
+        .sign(&chunk_message(stream_id, index, last, chunk))
+        .to_bytes()
+}
+
+/// 校验分块流一块的签名
+pub fn verify_chunk(
+    key: &VerifyingKey,
+    stream_id: &[u8; 32],
+    index: u32,
+    last: bool,
+    chunk: &[u8],
+    signature: &[u8],
+) -> PyResult<()> {
+    let signature = Signature::from_slice(signature)
+        .map_err(|_| SignatureError::new_err("发布者签名格式错误"))?;
+    key.verify_strict(&chunk_message(stream_id, index, last, chunk), &signature)
+        .map_err(|_| {
+            SignatureError::new_err(format!(
+                "第 {index} 块的发布者签名校验失败：密文被篡改、重排、截断或签名伪造"
+            ))
+        })
+}
+
+/// 已配置受信任发布者时拒绝未签名的数据（包括无法携带签名的旧版无头格式）
+pub fn check_unsigned_allowed() -> PyResult<()> {
+    if trusted().is_empty() {
+        Ok(())
+    } else {
+        Err(SignatureError::new_err(
+            "已启用发布者签名校验，拒绝未签名的密文",
+        ))
+    }
+}
+
+/// 生成发布者密钥对，返回 `(私钥, 公钥)`，均为 `b64:` 前缀字符串
+#[pyfunction]
+pub fn generate_publisher_key() -> (String, String) {
+    let signing_key = SigningKey::generate(&mut OsRng);
+    let secret = Zeroizing::new(signing_key.to_bytes());
+    (
+        format!("b64:{}", STANDARD.encode(&secret[..])),
+        encode_public_key(&signing_key.verifying_key().to_bytes()),
+    )
+}
+
+/// 由发布者私钥计算公钥
+#[pyfunction]
+pub fn publisher_public_key(signing_key: &str) -> PyResult<String> {
+    let signing_key = parse_signing_key(signing_key)?;
+    Ok(encode_public_key(&signing_key.verifying_key().to_bytes()))
+}
+
+/// 设置受信任的发布者公钥（替换原有列表）；非空时所有解密都要求有效的受信任签名，传入空列表关闭校验
+#[pyfunction]
+pub fn set_trusted_publishers(keys: Vec<String>) -> PyResult<()> {
+    let keys = keys
+        .iter()
+        .map(|spec| parse_public_key(spec).map(|key| key.to_bytes()))
+        .collect::<PyResult<Vec<_>>>()?;
+    *TRUSTED.write().unwrap_or_else(|e| e.into_inner()) = keys;
+    Ok(())
+}
+
+/// 当前受信任的发布者公钥
+#[pyfunction]
+pub fn trusted_publishers() -> Vec<String> {
+    trusted().iter().map(encode_public_key).collect()
+}
@@ -0,0 +1,705 @@
+//! 分块流式加解密（STREAM 构造），用于模型权重、数据文件等不适合整体载入内存的大文件
+//!
+//! 布局：
+//!
+//! ```text
+//! 容器头部（flags 含 FLAG_STREAM，扩展区记录分块大小） | salt(32) | chunk_0 | ... | chunk_n
+//! ```
+//!
+//! 每块为 `ciphertext||tag`，除最后一块外明文长度都等于分块大小，最后一块可以更短（可为空）。
+//! 带发布者签名（`FLAG_SIGNED`）的流在每块之后附加 `signature(64)`，见 `sign` 模块。
+//! 加密子密钥与密钥承诺由 salt 派生，每个流的子密钥都不同，因此 nonce 无需随机：
+//! 第 i 块的 nonce 为 `0.. | i(u32 大端) | last(1)`，头部作为每块的关联数据。
+//! 块序号使重排、删除中间块无法通过认证；最后一块标志使在块边界截断的密文同样无法通过认证。
+
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::PathBuf;
+
+use aes_gcm::aead::Payload;
+use ed25519_dalek::{SigningKey, VerifyingKey};
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::PyBytes;
+use rand::rngs::OsRng;
+use rand::RngCore;
+
+use crate::cipher::{commit_key, verify_commitment, AnyCipher};
+use crate::container::{
+    self, Algorithm, Context, Header, MAX_CHUNK_SIZE, MIN_CHUNK_SIZE, SIGNATURE_LEN,
+    STREAM_SALT_LEN, TAG_LEN,
+};
+use crate::error::{
+    AuthenticationError, MalformedCiphertextError, StrategyCryptoError, UnsupportedVersionError,
+};
+use crate::grace::OfflineGrace;
+use crate::key::parse_key;
+use crate::license::{self, License};
+use crate::revocation;
+use crate::secret::SecretBytes;
+use crate::sign::{self, parse_signing_key};
+
+pub const DEFAULT_CHUNK_SIZE: u32 = 64 * 1024;
+
+/// 第 `index` 块的 nonce
+fn chunk_nonce(nonce_len: usize, index: u32, last: bool) -> Vec<u8> {
+    let mut nonce = vec![0u8; nonce_len];
+    nonce[nonce_len - 5..nonce_len - 1].copy_from_slice(&index.to_be_bytes());
+    nonce[nonce_len - 1] = last as u8;
+    nonce
+}
+
+fn closed_file() -> PyErr {
+    PyValueError::new_err("I/O operation on closed file.")
+}
+
+/// 把 io 错误转回 Python 异常；由 `PyFile` 包装的 Python 异常原样取出
+pub fn io_err(e: io::Error) -> PyErr {
+    let kind = e.kind();
+    match e.into_inner() {
+        Some(inner) => match inner.downcast::<PyErr>() {
+            Ok(err) => *err,
+            Err(inner) => io::Error::new(kind, inner).into(),
+        },
+        None => io::Error::from(kind).into(),
+    }
+}
+
+/// 把 Python 文件对象适配为 std::io 接口，每次调用时获取 GIL
+struct PyFile(PyObject);
+
+impl Read for PyFile {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        Python::with_gil(|py| {
+            let data = self
+                .0
+                .bind(py)
+                .call_method1("read", (buf.len(),))
+                .map_err(io::Error::other)?;
+            let data: &[u8] = data.extract().map_err(io::Error::other)?;
+            if data.len() > buf.len() {
+                return Err(io::Error::other("read() 返回的数据超过请求长度"));
+            }
+            buf[..data.len()].copy_from_slice(data);
+            Ok(data.len())
+        })
+    }
+}
+
+impl Write for PyFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        Python::with_gil(|py| {
+            let written = self
+                .0
+                .bind(py)
+                .call_method1("write", (PyBytes::new_bound(py, buf),))
+                .map_err(io::Error::other)?;
+            // 原始 IO 对象可能只写入一部分；返回 None 的对象视为全部写入
+            let written: Option<usize> = written.extract().map_err(io::Error::other)?;
+            Ok(written.unwrap_or(buf.len()))
+        })
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Python::with_gil(|py| {
+            let file = self.0.bind(py);
+            if file.hasattr("flush").map_err(io::Error::other)? {
+                file.call_method0("flush").map_err(io::Error::other)?;
+            }
+            Ok(())
+        })
+    }
+}
+
+impl Seek for PyFile {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let (offset, whence) = match pos {
+            SeekFrom::Start(n) => (i64::try_from(n).map_err(io::Error::other)?, 0),
+            SeekFrom::Current(n) => (n, 1),
+            SeekFrom::End(n) => (n, 2),
+        };
+        Python::with_gil(|py| {
+            self.0
+                .bind(py)
+                .call_method1("seek", (offset, whence))
+                .and_then(|pos| pos.extract())
+                .map_err(io::Error::other)
+        })
+    }
+}
+
+/// `file` 为路径（str / os.PathLike）时由本对象打开并负责关闭，否则视为 Python 二进制文件对象，
+/// 关闭时不会关闭调用方传入的文件对象
+fn open_sink(file: &Bound<'_, PyAny>) -> PyResult<Box<dyn Write + Send>> {
+    match file.extract::<PathBuf>() {
+        Ok(path) => Ok(Box::new(File::create(path)?)),
+        Err(_) => Ok(Box::new(PyFile(file.clone().unbind()))),
+    }
+}
+
+fn open_source(file: &Bound<'_, PyAny>) -> PyResult<Box<dyn Source>> {
+    match file.extract::<PathBuf>() {
+        Ok(path) => Ok(Box::new(File::open(path)?)),
+        Err(_) => Ok(Box::new(PyFile(file.clone().unbind()))),
+    }
+}
+
+/// 读满 `buf` 或读到末尾，返回实际读取的字节数
+fn read_full(source: &mut impl Read, buf: &mut [u8]) -> PyResult<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match source.read(&mut buf[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(io_err(e)),
+        }
+    }
+    Ok(filled)
+}
+
+struct Encryptor {
+    sink: Box<dyn Write + Send>,
+    cipher: AnyCipher,
+    aad: Vec<u8>,
+    /// 发布者私钥与流标识，提供时每块之后附加签名
+    signer: Option<(SigningKey, [u8; 32])>,
+    nonce_len: usize,
+    chunk_size: usize,
+    /// 尚未加密的明文，最多一整块；写满后要等到后续数据到达才能确定它不是最后一块
+    buf: SecretBytes,
+    index: u32,
+}
+
+impl Encryptor {
+    fn write_chunk(&mut self, last: bool) -> PyResult<()> {
+        if !last && self.index == u32::MAX {
+            return Err(StrategyCryptoError::new_err("分块数量超过上限"));
+        }
+        let nonce = chunk_nonce(self.nonce_len, self.index, last);
+        let ct = self
+            .cipher
+            .encrypt(
+                &nonce,
+                Payload {
+                    msg: &self.buf,
+                    aad: &self.aad,
+                },
+            )
+            .map_err(|e| StrategyCryptoError::new_err(format!("加密失败: {e}")))?;
+        self.sink.write_all(&ct).map_err(io_err)?;
+        if let Some((signing_key, stream_id)) = &self.signer {
+            let signature = sign::sign_chunk(signing_key, stream_id, self.index, last, &ct);
+            self.sink.write_all(&signature).map_err(io_err)?;
+        }
+        self.buf.as_vec_mut().clear();
+        self.index += !last as u32;
+        Ok(())
+    }
+
+    fn write(&mut self, mut data: &[u8]) -> PyResult<()> {
+        while !data.is_empty() {
+            if self.buf.len() == self.chunk_size {
+                self.write_chunk(false)?;
+            }
+            let n = (self.chunk_size - self.buf.len()).min(data.len());
+            self.buf.as_vec_mut().extend_from_slice(&data[..n]);
+            data = &data[n..];
+        }
+        Ok(())
+    }
+
+    fn finish(mut self) -> PyResult<()> {
+        self.write_chunk(true)?;
+        self.sink.flush().map_err(io_err)
+    }
+}
+
+/// 分块加密写入器，用法与二进制文件对象相同：
+///
+/// ```python
+/// with EncryptWriter("weights.bin.enc", key) as f:
+///     f.write(data)
+/// ```
+///
+/// `signing_key` 为发布者 Ed25519 私钥，提供时每块都附带签名。
+/// `close()` 时写入带结束标志的最后一块；`with` 块内抛出异常或未调用 `close()` 时不写入最后一块，
+/// 得到的密文无法完整解密，避免把写了一半的数据当作完整文件。
+#[pyclass(module = "strategy_crypto")]
+pub struct EncryptWriter {
+    inner: Option<Encryptor>,
+}
+
+#[pymethods]
+impl EncryptWriter {
+    #[new]
+    #[pyo3(signature = (file, key, *, key_id = None, algorithm = None, context = None, chunk_size = DEFAULT_CHUNK_SIZE, signing_key = None))]
+    fn new(
+        file: &Bound<'_, PyAny>,
+        key: &str,
+        key_id: Option<&str>,
+        algorithm: Option<&str>,
+        context: Option<Context>,
+        chunk_size: u32,
+        signing_key: Option<&str>,
+    ) -> PyResult<Self> {
+        if !(MIN_CHUNK_SIZE..=MAX_CHUNK_SIZE).contains(&chunk_size) {
+            return Err(PyValueError::new_err(format!(
+                "chunk_size 需在 {MIN_CHUNK_SIZE}..={MAX_CHUNK_SIZE} 之间"
+            )));
+        }
+        let key = parse_key(key)?;
+        let signing_key = signing_key.map(parse_signing_key).transpose()?;
+        let mut salt = [0u8; STREAM_SALT_LEN];
+        OsRng.fill_bytes(&mut salt);
+        let (subkey, commitment) = commit_key(&key, &salt)?;
+        let mut header = Header::new(Algorithm::from_name(algorithm)?, key_id)?;
+        header.context = context;
+        header.commitment = Some(commitment);
+        header.chunk_size = Some(chunk_size);
+        header.signer = signing_key.as_ref().map(|k| k.verifying_key().to_bytes());
+        let aad = header.encode()?;
+        let signer = signing_key.map(|k| (k, sign::stream_id(&aad, &salt)));
+        let cipher = AnyCipher::new(header.alg, &subkey)?;
+        let mut sink = open_sink(file)?;
+        sink.write_all(&aad)
+            .and_then(|_| sink.write_all(&salt))
+            .map_err(io_err)?;
+        Ok(EncryptWriter {
+            inner: Some(Encryptor {
+                sink,
+                cipher,
+                aad,
+                signer,
+                nonce_len: header.nonce_len(),
+                chunk_size: chunk_size as usize,
+                buf: SecretBytes::with_capacity(chunk_size as usize),
+                index: 0,
+            }),
+        })
+    }
+
+    /// 写入明文，返回写入的字节数
+    fn write(&mut self, data: &[u8]) -> PyResult<usize> {
+        self.inner.as_mut().ok_or_else(closed_file)?.write(data)?;
+        Ok(data.len())
+    }
+
+    /// 只刷新底层文件；不足一块的明文要到 `close()` 时才会加密写出
+    fn flush(&mut self) -> PyResult<()> {
+        let inner = self.inner.as_mut().ok_or_else(closed_file)?;
+        inner.sink.flush().map_err(io_err)
+    }
+
+    fn close(&mut self) -> PyResult<()> {
+        match self.inner.take() {
+            Some(inner) => inner.finish(),
+            None => Ok(()),
+        }
+    }
+
+    #[getter]
+    fn closed(&self) -> bool {
+        self.inner.is_none()
+    }
+
+    fn writable(&self) -> bool {
+        true
+    }
+
+    fn readable(&self) -> bool {
+        false
+    }
+
+    fn __enter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    #[pyo3(signature = (exc_type, _exc_value, _traceback))]
+    fn __exit__(
+        &mut self,
+        exc_type: Option<&Bound<'_, PyAny>>,
+        _exc_value: Option<&Bound<'_, PyAny>>,
+        _traceback: Option<&Bound<'_, PyAny>>,
+    ) -> PyResult<bool> {
+        if exc_type.is_some() {
+            // 出错时丢弃未完成的流，不写入最后一块
+            self.inner = None;
+        } else {
+            self.close()?;
+        }
+        Ok(false)
+    }
+}
+
+/// 解密读取的数据源；路径打开的文件总是可定位，Python 文件对象视其 `seekable()` 而定
+trait Source: Read + Seek + Send {
+    fn seekable(&self) -> bool {
+        true
+    }
+}
+
+impl Source for File {}
+
+impl Source for PyFile {
+    fn seekable(&self) -> bool {
+        Python::with_gil(|py| {
+            self.0
+                .bind(py)
+                .call_method0("seekable")
+                .and_then(|r| r.extract())
+                .unwrap_or(false)
+        })
+    }
+}
+
+/// 随机访问所需的布局，首次定位时由底层文件长度推算
+#[derive(Clone, Copy)]
+struct Layout {
+    /// salt 之后第一块在底层文件中的位置
+    body_start: u64,
+    chunks: u64,
+    plaintext_len: u64,
+}
+
+struct Decryptor {
+    source: Box<dyn Source>,
+    cipher: AnyCipher,
+    aad: Vec<u8>,
+    /// 签名者公钥与流标识，带签名的流逐块校验
+    signer: Option<(VerifyingKey, [u8; 32])>,
+    nonce_len: usize,
+    chunk_size: usize,
+    /// 每块在明文之外的字节数，见 `Header::chunk_overhead`
+    overhead: usize,
+    /// 底层读取位置对应的块序号
+    next_index: u32,
+    /// 预读的下一块首字节，用于判断当前块是否为最后一块
+    lookahead: Option<u8>,
+    /// 自 salt 之后已从底层读取的字节数，用于推算第一块的位置
+    consumed: u64,
+    raw: Vec<u8>,
+    /// 已解密的块及其序号
+    plain: SecretBytes,
+    plain_index: Option<u32>,
+    /// 已知的最后一块序号
+    last_index: Option<u32>,
+    layout: Option<Layout>,
+    /// 明文读取位置
+    position: u64,
+    /// 某一块校验失败后不再继续输出，避免把失败当作文件结束
+    failed: bool,
+}
+
+impl Decryptor {
+    /// 在底层当前位置读取并解密第 `next_index` 块
+    fn next_chunk(&mut self) -> PyResult<()> {
+        if self.failed {
+            return Err(AuthenticationError::new_err("密文流此前已校验失败"));
+        }
+        self.failed = true;
+        self.plain_index = None;
+        let full = self.chunk_size + self.overhead;
+        self.raw.clear();
+        self.raw.extend(self.lookahead.take());
+        let start = self.raw.len();
+        self.raw.resize(full + 1, 0);
+        let n = read_full(&mut self.source, &mut self.raw[start..])?;
+        self.consumed += n as u64;
+        self.raw.truncate(start + n);
+        let last = self.raw.len() <= full;
+        if !last {
+            self.lookahead = self.raw.pop();
+        }
+        if self.raw.len() < self.overhead {
+            return Err(MalformedCiphertextError::new_err("密文流被截断"));
+        }
+        let index = self.next_index;
+        if !last && index == u32::MAX {
+            return Err(MalformedCiphertextError::new_err("分块数量超过上限"));
+        }
+        let mut chunk = &self.raw[..];
+        if let Some((key, stream_id)) = &self.signer {
+            let (signed, signature) = chunk.split_at(chunk.len() - SIGNATURE_LEN);
+            sign::verify_chunk(key, stream_id, index, last, signed, signature)?;
+            chunk = signed;
+        }
+        let nonce = chunk_nonce(self.nonce_len, index, last);
+        let buf = self.plain.as_vec_mut();
+        buf.clear();
+        buf.extend_from_slice(chunk);
+        self.cipher
+            .decrypt_in_place(&nonce, &self.aad, buf)
+            .map_err(|_| {
+                AuthenticationError::new_err(format!(
+                    "认证失败：第 {index} 块校验失败（密钥错误、密文被篡改、重排或截断）"
+                ))
+            })?;
+        self.plain_index = Some(index);
+        if last {
+            self.last_index = Some(index);
+        } else {
+            self.next_index += 1;
+        }
+        self.failed = false;
+        Ok(())
+    }
+
+    fn layout(&mut self) -> PyResult<Layout> {
+        if let Some(layout) = self.layout {
+            return Ok(layout);
+        }
+        let pos = self.source.stream_position().map_err(io_err)?;
+        let body_start = pos - self.consumed;
+        let end = self.source.seek(SeekFrom::End(0)).map_err(io_err)?;
+        self.source.seek(SeekFrom::Start(pos)).map_err(io_err)?;
+        let body_len = end.saturating_sub(body_start);
+        let (chunks, plaintext_len) =
+            container::stream_layout(body_len, self.chunk_size as u32, self.overhead);
+        if chunks == 0 || body_len < chunks * self.overhead as u64 {
+            return Err(MalformedCiphertextError::new_err("密文流被截断"));
+        }
+        if chunks - 1 > u32::MAX as u64 {
+            return Err(MalformedCiphertextError::new_err("分块数量超过上限"));
+        }
+        let layout = Layout {
+            body_start,
+            chunks,
+            plaintext_len,
+        };
+        self.layout = Some(layout);
+        Ok(layout)
+    }
+
+    /// 确保第 `index` 块已解密；超出末尾时返回 false。
+    /// 顺序读取直接接着底层位置读，否则按布局定位到该块
+    fn load(&mut self, index: u64) -> PyResult<bool> {
+        if self.plain_index.map(u64::from) == Some(index) {
+            return Ok(true);
+        }
+        if self.last_index.is_some_and(|last| index > last as u64) {
+            return Ok(false);
+        }
+        if index != self.next_index as u64 {
+            let chunk_size = self.chunk_size as u64;
+            let layout = self.layout()?;
+            if index >= layout.chunks {
+                return Ok(false);
+            }
+            let offset = index * (chunk_size + self.overhead as u64);
+            let target = layout.body_start + offset;
+            self.source.seek(SeekFrom::Start(target)).map_err(io_err)?;
+            self.lookahead = None;
+            self.consumed = offset;
+            self.next_index = index as u32;
+        }
+        self.next_chunk()?;
+        Ok(true)
+    }
+
+    /// 从当前位置读取至多 `size` 字节明文，`None` 表示读到末尾。
+    /// 可定位时按剩余明文长度一次分配好缓冲区，否则由 `SecretBytes` 按需换用更大的缓冲区
+    fn read(&mut self, size: Option<usize>) -> PyResult<SecretBytes> {
+        let capacity = if self.source.seekable() {
+            let layout = self.layout()?;
+            let remaining = layout.plaintext_len.saturating_sub(self.position);
+            let remaining = usize::try_from(remaining).unwrap_or(usize::MAX);
+            size.map_or(remaining, |size| size.min(remaining))
+        } else {
+            size.map_or(self.chunk_size, |size| size.min(self.chunk_size))
+        };
+        let mut out = SecretBytes::with_capacity(capacity);
+        let chunk_size = self.chunk_size as u64;
+        while size.is_none_or(|size| out.len() < size) {
+            let index = self.position / chunk_size;
+            let offset = (self.position % chunk_size) as usize;
+            if !self.load(index)? || offset >= self.plain.len() {
+                break;
+            }
+            let avail = &self.plain[offset..];
+            let n = size.map_or(avail.len(), |size| (size - out.len()).min(avail.len()));
+            out.extend_from_slice(&avail[..n]);
+            self.position += n as u64;
+        }
+        Ok(out)
+    }
+}
+
+/// 分块解密读取器，用法与只读二进制文件对象相同。
+///
+/// 创建时即校验密钥承诺并解密第一块，密钥错误或头部被篡改会在构造时报错；
+/// 之后每块在返回数据前都经过认证，被截断或重排的密文在读到对应位置时抛出 `AuthenticationError`。
+///
+/// 配置了授权公钥时必须传入 `license` 与 `mode`，授权须覆盖密文绑定的 strategy_id。
+///
+/// 底层文件可定位时支持 `seek()` / `read_at()`：按偏移直接定位到覆盖所需范围的块，
+/// 只认证并解密这些块。末尾块由文件长度确定并带有结束标志，截断同样会被发现。
+#[pyclass(module = "strategy_crypto")]
+pub struct DecryptReader {
+    inner: Option<Decryptor>,
+}
+
+impl DecryptReader {
+    fn inner(&mut self) -> PyResult<&mut Decryptor> {
+        self.inner.as_mut().ok_or_else(closed_file)
+    }
+}
+
+#[pymethods]
+impl DecryptReader {
+    #[new]
+    #[pyo3(signature = (file, key, *, expected = None, allow_unbound = false, license = None, mode = None, grace = None))]
+    fn new(
+        file: &Bound<'_, PyAny>,
+        key: &str,
+        expected: Option<Context>,
+        allow_unbound: bool,
+        license: Option<PyRef<'_, License>>,
+        mode: Option<&str>,
+        grace: Option<PyRef<'_, OfflineGrace>>,
+    ) -> PyResult<Self> {
+        let key = parse_key(key)?;
+        let mut source = open_source(file)?;
+        let (header, aad) = Header::read_from(&mut source)?;
+        let Some(chunk_size) = header.chunk_size else {
+            return Err(StrategyCryptoError::new_err(
+                "不是分块流格式密文，请使用 decrypt_bytes",
+            ));
+        };
+        if header.compression.is_some() || header.padding.is_some() {
+            return Err(UnsupportedVersionError::new_err("分块流不支持压缩或填充"));
+        }
+        let signer = match &header.signer {
+            Some(signer) => Some(sign::signer_key(signer)?),
+            None => {
+                sign::check_unsigned_allowed()?;
+                None
+            }
+        };
+        revocation::check_header(&header)?;
+        license::check_bound_license(
+            license.as_deref(),
+            mode,
+            grace.as_deref(),
+            header.context.as_ref(),
+        )?;
+        let Some(commitment) = &header.commitment else {
+            return Err(MalformedCiphertextError::new_err("分块流缺少密钥承诺"));
+        };
+        let mut salt = [0u8; STREAM_SALT_LEN];
+        if read_full(&mut source, &mut salt)? < STREAM_SALT_LEN {
+            return Err(MalformedCiphertextError::new_err("密文流被截断"));
+        }
+        let subkey = verify_commitment(&key, &salt, commitment)?;
+        if let Some(expected) = &expected {
+            container::check_context(Some(&header), expected, allow_unbound)?;
+        }
+        let chunk_size = chunk_size as usize;
+        let overhead = header.chunk_overhead();
+        let signer = signer.map(|key| (key, sign::stream_id(&aad, &salt)));
+        let mut inner = Decryptor {
+            source,
+            cipher: AnyCipher::new(header.alg, &subkey)?,
+            aad,
+            signer,
+            nonce_len: header.nonce_len(),
+            chunk_size,
+            overhead,
+            next_index: 0,
+            lookahead: None,
+            consumed: 0,
+            raw: Vec::with_capacity(chunk_size + overhead + 1),
+            plain: SecretBytes::with_capacity(chunk_size + TAG_LEN),
+            plain_index: None,
+            last_index: None,
+            layout: None,
+            position: 0,
+            failed: false,
+        };
+        inner.next_chunk()?;
+        Ok(DecryptReader { inner: Some(inner) })
+    }
+
+    /// 读取至多 `size` 字节明文；`size` 为负数或省略时读到末尾
+    #[pyo3(signature = (size = -1))]
+    fn read(&mut self, py: Python<'_>, size: isize) -> PyResult<Py<PyBytes>> {
+        let out = self.inner()?.read(usize::try_from(size).ok())?;
+        Ok(PyBytes::new_bound(py, &out).unbind())
+    }
+
+    /// 读取明文 `[offset, offset + size)`，不改变当前读取位置
+    fn read_at(&mut self, py: Python<'_>, offset: u64, size: usize) -> PyResult<Py<PyBytes>> {
+        let inner = self.inner()?;
+        let position = inner.position;
+        inner.position = offset;
+        let out = inner.read(Some(size));
+        inner.position = position;
+        Ok(PyBytes::new_bound(py, &out?).unbind())
+    }
+
+    /// 移动明文读取位置，`whence` 含义与 `io.IOBase.seek` 相同；定位本身不解密，读取时才解密对应的块
+    #[pyo3(signature = (offset, whence = 0))]
+    fn seek(&mut self, offset: i64, whence: u8) -> PyResult<u64> {
+        let inner = self.inner()?;
+        let base = match whence {
+            0 => 0,
+            1 => inner.position,
+            2 => inner.layout()?.plaintext_len,
+            _ => {
+                return Err(PyValueError::new_err(format!(
+                    "invalid whence ({whence}, should be 0, 1 or 2)"
+                )))
+            }
+        };
+        let position = base
+            .checked_add_signed(offset)
+            .ok_or_else(|| PyValueError::new_err(format!("negative seek position {offset}")))?;
+        inner.position = position;
+        Ok(position)
+    }
+
+    fn tell(&mut self) -> PyResult<u64> {
+        Ok(self.inner()?.position)
+    }
+
+    /// 明文总长度；需要底层文件可定位
+    fn size(&mut self) -> PyResult<u64> {
+        Ok(self.inner()?.layout()?.plaintext_len)
+    }
+
+    fn seekable(&mut self) -> PyResult<bool> {
+        Ok(self.inner()?.source.seekable())
+    }
+
+    fn close(&mut self) {
+        self.inner = None;
+    }
+
+    #[getter]
+    fn closed(&self) -> bool {
+        self.inner.is_none()
+    }
+
+    fn readable(&self) -> bool {
+        true
+    }
+
+    fn writable(&self) -> bool {
+        false
+    }
+
+    fn __enter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    #[pyo3(signature = (_exc_type, _exc_value, _traceback))]
+    fn __exit__(
+        &mut self,
+        _exc_type: Option<&Bound<'_, PyAny>>,
+        _exc_value: Option<&Bound<'_, PyAny>>,
+        _traceback: Option<&Bound<'_, PyAny>>,
+    ) -> bool {
+        self.close();
+        false
+    }
+}
@@ -0,0 +1,146 @@
+//! 设备密钥封装
+//!
+//! 服务端原本把策略的对称密钥直接放在 JSON 响应里下发，任何能看到响应的人都能拿到明文密钥。
+//! 现在每台客户端生成一对 X25519 设备密钥，服务端用设备公钥按 HPKE 的思路封装内容密钥：
+//! 临时私钥与设备公钥做 ECDH，HKDF-SHA256 派生一次性密钥，再用 ChaCha20-Poly1305 加密内容密钥。
+//! 客户端把封装结果作为 `sealed:<base64>` 密钥字符串使用，解析密钥时用本机设备私钥解封，
+//! 对称密钥只在 Rust 内存中出现。
+//!
+//! 封装布局：
+//!
+//! ```text
+//! version(1) | enc(32，临时公钥) | ct(32) || tag(16)
+//! ```
+//!
+//! 一次性密钥只用一次，nonce 固定为全零；`version | enc` 作为关联数据参与认证。
+
+use std::sync::RwLock;
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::ChaCha20Poly1305;
+use hkdf::Hkdf;
+use pyo3::prelude::*;
+use rand::rngs::OsRng;
+use sha2::Sha256;
+use x25519_dalek::{EphemeralSecret, PublicKey, StaticSecret};
+use zeroize::Zeroizing;
+
+use crate::error::InvalidKeyError;
+use crate::key::{parse_key, Key, KEY_LEN};
+use crate::secret::SecretKey;
+
+const WRAP_VERSION: u8 = 1;
+const ENC_LEN: usize = 32;
+const TAG_LEN: usize = 16;
+const WRAPPED_LEN: usize = 1 + ENC_LEN + KEY_LEN + TAG_LEN;
+
+/// HKDF info 的域分隔前缀
+const WRAP_LABEL: &[u8] = b"bullet-trade/key-wrap/v1";
+
+/// 本机设备私钥，用于解封 `sealed:` 密钥
+static DEVICE_KEY: RwLock<Option<StaticSecret>> = RwLock::new(None);
+
+fn encode_key(key: &[u8; 32]) -> String {
+    format!("b64:{}", STANDARD.encode(key))
+}
+
+fn parse_device_secret(spec: &str) -> PyResult<StaticSecret> {
+    Ok(StaticSecret::from(*parse_key(spec)?))
+}
+
+/// 由 ECDH 共享值派生一次性密钥；info 绑定临时公钥与设备公钥
+fn wrapping_cipher(
+    shared: &[u8; 32],
+    enc: &PublicKey,
+    recipient: &PublicKey,
+) -> Result<ChaCha20Poly1305, String> {
+    let info = [WRAP_LABEL, enc.as_bytes(), recipient.as_bytes()].concat();
+    let mut okm = SecretKey::zeroed();
+    Hkdf::<Sha256>::new(None, shared)
+        .expand(&info, &mut okm[..])
+        .map_err(|e| format!("HKDF 派生失败（{e}）"))?;
+    ChaCha20Poly1305::new_from_slice(&okm[..]).map_err(|e| e.to_string())
+}
+
+/// 用本机设备私钥解封 `sealed:` 后的 base64 内容，错误信息供密钥解析拼接
+pub fn unwrap_key(wrapped: &str) -> Result<Key, String> {
+    let raw = STANDARD
+        .decode(wrapped)
+        .map_err(|e| format!("解码失败（{e}）"))?;
+    if raw.len() != WRAPPED_LEN || raw[0] != WRAP_VERSION {
+        return Err("不是有效的封装密钥".to_string());
+    }
+    let guard = DEVICE_KEY.read().unwrap_or_else(|e| e.into_inner());
+    let device = guard
+        .as_ref()
+        .ok_or("未配置设备私钥，请先调用 set_device_key")?;
+    let (aad, ct) = raw.split_at(1 + ENC_LEN);
+    let mut enc = [0u8; ENC_LEN];
+    enc.copy_from_slice(&aad[1..]);
+    let enc = PublicKey::from(enc);
+    let shared = device.diffie_hellman(&enc);
+    if !shared.was_contributory() {
+        return Err("封装密钥的临时公钥无效".to_string());
+    }
+    let pt = Zeroizing::new(
+        wrapping_cipher(shared.as_bytes(), &enc, &PublicKey::from(device))?
+            .decrypt(&Default::default(), Payload { msg: ct, aad })
+            .map_err(|_| "解封失败：封装密钥不是发给本机设备的或已被篡改".to_string())?,
+    );
+    let mut key = SecretKey::zeroed();
+    key.copy_from_slice(&pt);
+    Ok(key)
+}
+
+/// 生成设备密钥对，返回 `(私钥, 公钥)`，均为 `b64:` 前缀字符串
+#[pyfunction]
+pub fn generate_device_key() -> (String, String) {
+    let secret = StaticSecret::random_from_rng(OsRng);
+    let public = PublicKey::from(&secret);
+    let secret = Zeroizing::new(secret.to_bytes());
+    (encode_key(&secret), encode_key(public.as_bytes()))
+}
+
+/// 由设备私钥计算公钥
+#[pyfunction]
+pub fn device_public_key(private_key: &str) -> PyResult<String> {
+    let secret = parse_device_secret(private_key)?;
+    Ok(encode_key(PublicKey::from(&secret).as_bytes()))
+}
+
+/// 设置本机设备私钥（传入 None 清除），之后 `sealed:` 密钥字符串可用于所有解密接口
+#[pyfunction]
+pub fn set_device_key(private_key: Option<&str>) -> PyResult<()> {
+    let secret = private_key.map(parse_device_secret).transpose()?;
+    *DEVICE_KEY.write().unwrap_or_else(|e| e.into_inner()) = secret;
+    Ok(())
+}
+
+/// 把内容密钥封装给指定设备公钥（服务端使用），返回 `sealed:<base64>` 密钥字符串
+#[pyfunction]
+pub fn wrap_key(key: &str, device_public_key: &str) -> PyResult<String> {
+    let key = parse_key(key)?;
+    let recipient = PublicKey::from(*parse_key(device_public_key)?);
+    let ephemeral = EphemeralSecret::random_from_rng(OsRng);
+    let enc = PublicKey::from(&ephemeral);
+    let shared = ephemeral.diffie_hellman(&recipient);
+    if !shared.was_contributory() {
+        return Err(InvalidKeyError::new_err("无效的设备公钥"));
+    }
+    let mut out = Vec::with_capacity(WRAPPED_LEN);
+    out.push(WRAP_VERSION);
+    out.extend_from_slice(enc.as_bytes());
+    let ct = wrapping_cipher(shared.as_bytes(), &enc, &recipient)
+        .map_err(InvalidKeyError::new_err)?
+        .encrypt(
+            &Default::default(),
+            Payload {
+                msg: &key[..],
+                aad: &out,
+            },
+        )
+        .map_err(|_| InvalidKeyError::new_err("封装密钥失败"))?;
+    out.extend_from_slice(&ct);
+    Ok(format!("sealed:{}", STANDARD.encode(&out)))
+}
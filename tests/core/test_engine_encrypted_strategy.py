@@ -0,0 +1,374 @@
+"""
+BacktestEngine 加载策略与解密相关的测试。
+"""
+
+from __future__ import annotations
+
+import base64
+import os
+import sys
+import time
+import types
+
+import pytest
+
+from bullet_trade.core import engine as engine_module
+from bullet_trade.core.engine import BacktestEngine
+from bullet_trade.core.globals import g
+
+
+PLAINTEXT_SOURCE = """
+def initialize(context):
+    g.loaded = "plaintext"
+"""
+
+
+CONTAINER_SOURCE = """
+def initialize(context):
+    g.loaded = "container"
+"""
+
+
+BUNDLE_MAIN = """
+from helper import VALUE
+
+
+def initialize(context):
+    g.loaded = VALUE
+"""
+
+
+def new_key() -> str:
+    return "b64:" + base64.b64encode(os.urandom(32)).decode()
+
+
+def write_bundle(root, value: str) -> None:
+    root.mkdir(parents=True, exist_ok=True)
+    (root / "main.py").write_text(BUNDLE_MAIN)
+    (root / "helper.py").write_text(f"VALUE = {value!r}\n")
+
+
+def make_engine(blob: bytes, key: str, strategy_id: str = "demo") -> BacktestEngine:
+    return BacktestEngine(
+        strategy_file=f"remote://{strategy_id}",
+        start_date="2025-01-02",
+        end_date="2025-01-03",
+        strategy_blob=blob,
+        strategy_key=key,
+    )
+
+
+@pytest.fixture
+def sc(monkeypatch):
+    """已编译的扩展；隔离策略加密相关的环境变量，并卸载测试中安装的策略包"""
+    for name in ("STRATEGY_KEY", "STRATEGY_KEYS", "STRATEGY_LICENSE"):
+        monkeypatch.delenv(name, raising=False)
+    strategy_crypto = pytest.importorskip("strategy_crypto")
+    yield strategy_crypto
+    sys.meta_path[:] = [
+        f for f in sys.meta_path if not isinstance(f, strategy_crypto.BundleImporter)
+    ]
+    for name in ("main", "helper"):
+        sys.modules.pop(name, None)
+
+
+@pytest.fixture
+def without_extension(monkeypatch):
+    """模拟从仓库根目录运行且扩展未编译：strategy_crypto 被导入为空的命名空间包"""
+    monkeypatch.setitem(sys.modules, "strategy_crypto", types.ModuleType("strategy_crypto"))
+
+
+@pytest.mark.unit
+def test_load_plaintext_strategy_without_extension(tmp_path, without_extension):
+    path = tmp_path / "strategy.py"
+    path.write_text(PLAINTEXT_SOURCE)
+    engine = BacktestEngine(strategy_file=str(path), start_date="2025-01-02", end_date="2025-01-03")
+    engine.load_strategy()
+    engine.initialize_func(None)
+    assert g.loaded == "plaintext"
+
+
+@pytest.mark.unit
+def test_fetch_remote_key_without_extension(monkeypatch, without_extension):
+    requests_seen = []
+
+    class Response:
+        def raise_for_status(self):
+            pass
+
+        def json(self):
+            return {"key_b64": "AAAA"}
+
+    def fake_get(url, headers=None, params=None, timeout=None):
+        requests_seen.append(params)
+        return Response()
+
+    monkeypatch.setattr(engine_module.requests, "get", fake_get)
+    assert engine_module.fetch_remote_strategy_key("demo") == "b64:AAAA"
+    # 扩展未安装时不发送设备公钥，服务端按旧协议下发密钥
+    assert requests_seen == [{}]
+
+
+@pytest.mark.unit
+@pytest.mark.parametrize(
+    "data, key",
+    [
+        ({"sealed_key": "S", "key_b64": "B"}, "sealed:S"),
+        ({"key_b64": "B"}, "b64:B"),
+        ({}, None),
+        ("not a dict", None),
+    ],
+)
+def test_strategy_key_from_response(data, key):
+    assert engine_module.strategy_key_from_response(None, data) == key
+
+
+@pytest.mark.unit
+@pytest.mark.parametrize("installed", [True, False])
+def test_plaintext_blob_is_executed_instead_of_file(tmp_path, monkeypatch, installed):
+    if installed:
+        pytest.importorskip("strategy_crypto")
+    else:
+        monkeypatch.setitem(sys.modules, "strategy_crypto", types.ModuleType("strategy_crypto"))
+    path = tmp_path / "strategy.py"
+    path.write_text(PLAINTEXT_SOURCE.replace("plaintext", "disk"))
+    engine = BacktestEngine(
+        strategy_file=str(path),
+        start_date="2025-01-02",
+        end_date="2025-01-03",
+        strategy_blob=PLAINTEXT_SOURCE.encode(),
+    )
+    engine.load_strategy()
+    engine.initialize_func(None)
+    assert g.loaded == "plaintext"
+
+
+@pytest.mark.unit
+def test_remote_strategy_without_extension(without_extension):
+    engine = BacktestEngine(
+        strategy_file="remote://demo",
+        start_date="2025-01-02",
+        end_date="2025-01-03",
+        strategy_blob=b"\x00encrypted",
+        strategy_key="b64:AAAA",
+    )
+    with pytest.raises(ValueError, match="strategy_crypto"):
+        engine.load_strategy()
+
+
+@pytest.fixture
+def license_authority(monkeypatch, tmp_path):
+    """配置授权公钥（测试结束后清空），返回 (strategy_crypto, 授权签发私钥)"""
+    sc = pytest.importorskip("strategy_crypto")
+    signing_key, public_key = sc.generate_publisher_key()
+    monkeypatch.setenv("STRATEGY_LICENSE_AUTHORITIES", public_key)
+    monkeypatch.setenv("STRATEGY_REVOCATION_CACHE", str(tmp_path / "revocations.token"))
+    monkeypatch.setenv("STRATEGY_GRACE_FILE", str(tmp_path / "grace.state"))
+    monkeypatch.delenv("STRATEGY_LICENSE", raising=False)
+    yield sc, signing_key
+    sc.set_license_authorities([])
+
+
+def _encrypted_local_strategy(sc, tmp_path):
+    key = "b64:" + "A" * 43 + "="
+    path = tmp_path / "strategy.py"
+    path.write_bytes(
+        sc.encrypt_bytes_with_context(key, PLAINTEXT_SOURCE.encode(), {"strategy_id": "demo"})
+    )
+    return BacktestEngine(
+        strategy_file=str(path),
+        start_date="2025-01-02",
+        end_date="2025-01-03",
+        strategy_key=key,
+    )
+
+
+@pytest.mark.unit
+def test_local_strategy_requires_license_when_authorities_configured(tmp_path, license_authority):
+    sc, _ = license_authority
+    engine = _encrypted_local_strategy(sc, tmp_path)
+    with pytest.raises(ValueError, match="STRATEGY_LICENSE"):
+        engine.load_strategy()
+    # 绕过 load_strategy 直接调用扩展同样被拒绝
+    with pytest.raises(sc.LicenseError):
+        sc.decrypt_bytes(engine.strategy_key, (tmp_path / "strategy.py").read_bytes())
+
+
+@pytest.mark.unit
+@pytest.mark.parametrize("strategy_id, loads", [("demo", True), ("other", False)])
+def test_local_strategy_license_checked_against_bound_strategy_id(
+    tmp_path, monkeypatch, license_authority, strategy_id, loads
+):
+    sc, signing_key = license_authority
+    monkeypatch.setenv(
+        "STRATEGY_LICENSE",
+        sc.issue_license(signing_key, "tester", [strategy_id], ["backtest"], 2**40),
+    )
+    # 取密钥时服务端签发的时间凭证确认联网，离线宽限期从此开始计算
+    time_token = sc.issue_time_token(signing_key, sc.machine_id(), sc.time_challenge())
+    engine_module.record_server_time(sc, time_token)
+    engine = _encrypted_local_strategy(sc, tmp_path)
+    if loads:
+        engine.load_strategy()
+        engine.initialize_func(None)
+        assert g.loaded == "plaintext"
+    else:
+        with pytest.raises(ValueError, match="demo"):
+            engine.load_strategy()
+
+
+@pytest.mark.unit
+def test_load_container_single_file(sc):
+    key = new_key()
+    blob = sc.encrypt_bytes_with_context(key, CONTAINER_SOURCE.encode(), {"strategy_id": "demo"})
+    engine = make_engine(blob, key)
+    engine.load_strategy()
+    engine.initialize_func(None)
+    assert g.loaded == "container"
+
+
+@pytest.mark.unit
+def test_load_local_container_file(sc, tmp_path, monkeypatch):
+    key = new_key()
+    path = tmp_path / "strategy.py.enc"
+    path.write_bytes(sc.encrypt_bytes(key, CONTAINER_SOURCE.encode()))
+    monkeypatch.setenv("STRATEGY_KEY", key)
+    engine = BacktestEngine(strategy_file=str(path), start_date="2025-01-02", end_date="2025-01-03")
+    engine.load_strategy()
+    engine.initialize_func(None)
+    assert g.loaded == "container"
+
+
+@pytest.mark.unit
+def test_remote_container_bound_to_other_strategy(sc):
+    key = new_key()
+    blob = sc.encrypt_bytes_with_context(key, CONTAINER_SOURCE.encode(), {"strategy_id": "other"})
+    with pytest.raises(ValueError, match="策略解密失败"):
+        make_engine(blob, key).load_strategy()
+
+
+@pytest.mark.unit
+def test_local_encrypted_file_without_key(sc, tmp_path):
+    path = tmp_path / "strategy.py.enc"
+    path.write_bytes(sc.encrypt_bytes(new_key(), CONTAINER_SOURCE.encode()))
+    engine = BacktestEngine(strategy_file=str(path), start_date="2025-01-02", end_date="2025-01-03")
+    # 不再回退到内置默认密钥
+    with pytest.raises(ValueError, match="未配置密钥"):
+        engine.load_strategy()
+
+
+@pytest.mark.unit
+def test_local_file_decrypted_with_keyring(sc, tmp_path, monkeypatch):
+    ring = sc.Keyring()
+    ring.add("old", new_key())
+    key = new_key()
+    ring.add("new", key, primary=True)
+    path = tmp_path / "strategy.py.enc"
+    path.write_bytes(ring.encrypt(CONTAINER_SOURCE.encode()))
+    # 按密文头部的 key_id 从 STRATEGY_KEYS 中选择密钥
+    monkeypatch.setenv("STRATEGY_KEYS", f"old={new_key()},new={key}")
+    engine = BacktestEngine(strategy_file=str(path), start_date="2025-01-02", end_date="2025-01-03")
+    engine.load_strategy()
+    engine.initialize_func(None)
+    assert g.loaded == "container"
+
+
+@pytest.mark.unit
+def test_load_bundle_replaces_previous_bundle(sc, tmp_path):
+    key = new_key()
+    for value in ("first", "second"):
+        root = tmp_path / value
+        write_bundle(root, value)
+        blob = sc.pack_bundle(root, key, entry_point="main", context={"strategy_id": "demo"})
+        engine = make_engine(blob, key)
+        engine.load_strategy()
+        engine.initialize_func(None)
+        # 第二次加载必须导入新包中的 helper，而不是沿用旧包已导入的模块
+        assert g.loaded == value
+    importers = [f for f in sys.meta_path if isinstance(f, sc.BundleImporter)]
+    assert len(importers) == 1
+
+
+@pytest.mark.unit
+def test_bundle_bound_to_other_strategy(sc, tmp_path):
+    key = new_key()
+    write_bundle(tmp_path / "bundle", "x")
+    blob = sc.pack_bundle(
+        tmp_path / "bundle", key, entry_point="main", context={"strategy_id": "other"}
+    )
+    with pytest.raises(ValueError, match="策略解密失败"):
+        make_engine(blob, key).load_strategy()
+
+
+@pytest.mark.unit
+def test_remote_strategy_requires_license(monkeypatch, license_authority):
+    sc, signing_key = license_authority
+    monkeypatch.setattr(engine_module, "refresh_revocation_list", lambda *args, **kwargs: None)
+    key = new_key()
+    blob = sc.encrypt_bytes_with_context(key, CONTAINER_SOURCE.encode(), {"strategy_id": "demo"})
+
+    # 配置了授权公钥却没有授权时拒绝运行，不退回普通解密
+    with pytest.raises(ValueError, match="STRATEGY_LICENSE"):
+        make_engine(blob, key).load_strategy()
+
+    try:
+        machine = sc.machine_id()
+    except sc.LicenseError:
+        pytest.skip("无法读取本机机器标识")
+    token = sc.issue_license(
+        signing_key, "alice", ["demo"], ["backtest"], int(time.time()) + 3600, machine=machine
+    )
+    monkeypatch.setenv("STRATEGY_LICENSE", token)
+    # 从未用服务端时间凭证确认联网时无法离线运行
+    with pytest.raises(ValueError, match="策略解密失败"):
+        make_engine(blob, key).load_strategy()
+
+    engine_module.record_server_time(
+        sc, sc.issue_time_token(signing_key, machine, sc.time_challenge())
+    )
+    engine = make_engine(blob, key)
+    engine.load_strategy()
+    engine.initialize_func(None)
+    assert g.loaded == "container"
+
+
+@pytest.mark.unit
+def test_fetch_remote_key_with_extension(monkeypatch, license_authority):
+    sc, signing_key = license_authority
+    private_key, public_key = sc.generate_device_key()
+    monkeypatch.setenv("STRATEGY_DEVICE_KEY", private_key)
+    key = new_key()
+    requests_seen = []
+
+    class Response:
+        def __init__(self, params):
+            self.params = params
+
+        def raise_for_status(self):
+            pass
+
+        def json(self):
+            # 服务端封装密钥，并对客户端挑战签发时间凭证
+            params = self.params
+            return {
+                "sealed_key": sc.wrap_key(key, params["device_public_key"])[len("sealed:") :],
+                "time_token": sc.issue_time_token(
+                    signing_key, params["machine_id"], params["time_nonce"]
+                ),
+            }
+
+    def fake_get(url, headers=None, params=None, timeout=None):
+        requests_seen.append(params)
+        return Response(params)
+
+    monkeypatch.setattr(engine_module.requests, "get", fake_get)
+    sealed = engine_module.fetch_remote_strategy_key("demo")
+    assert sealed.startswith("sealed:")
+    assert requests_seen[0]["device_public_key"] == public_key
+    blob = sc.encrypt_bytes_with_context(key, b"src", {"strategy_id": "demo"})
+    sc.set_license_authorities([])
+    try:
+        assert bytes(sc.decrypt_bytes(sealed, blob)) == b"src"
+    finally:
+        sc.set_device_key(None)
+    assert engine_module.get_offline_grace(sc).last_online is not None
@@ -0,0 +1,1093 @@
+"""
+strategy_crypto 扩展模块测试。
+"""
+
+from __future__ import annotations
+
+import base64
+import importlib
+import io
+import os
+import sys
+import time
+import uuid
+
+import pytest
+
+sc = pytest.importorskip("strategy_crypto")
+
+
+def new_key() -> str:
+    return "b64:" + base64.b64encode(os.urandom(32)).decode()
+
+
+def legacy_blob(key: str, plaintext: bytes) -> bytes:
+    """旧版无头格式：nonce(12) || ciphertext || tag，不带关联数据"""
+    aead = pytest.importorskip("cryptography.hazmat.primitives.ciphers.aead")
+    nonce = os.urandom(12)
+    raw_key = base64.b64decode(key[len("b64:") :])
+    return nonce + aead.AESGCM(raw_key).encrypt(nonce, plaintext, None)
+
+
+def flip(blob: bytes, index: int) -> bytes:
+    out = bytearray(blob)
+    out[index] ^= 0x01
+    return bytes(out)
+
+
+@pytest.fixture(autouse=True)
+def reset_crypto_state():
+    """受信任公钥与设备私钥是进程级状态，每个用例前后清空"""
+    sc.set_trusted_publishers([])
+    sc.set_license_authorities([])
+    sc.set_device_key(None)
+    yield
+    sc.set_trusted_publishers([])
+    sc.set_license_authorities([])
+    sc.set_device_key(None)
+
+
+@pytest.fixture
+def authority():
+    """配置一把授权公钥并返回对应私钥"""
+    signing_key, public_key = sc.generate_publisher_key()
+    sc.set_license_authorities([public_key])
+    return signing_key
+
+
+@pytest.fixture
+def machine():
+    try:
+        return sc.machine_id()
+    except sc.LicenseError:
+        pytest.skip("无法读取本机机器标识")
+
+
+@pytest.mark.unit
+class TestContainer:
+    """容器格式"""
+
+    def test_round_trip(self):
+        key = new_key()
+        blob = sc.encrypt_bytes(key, b"def initialize(context): pass")
+        assert sc.is_encrypted(blob)
+        info = sc.inspect(blob)
+        assert info["format_version"] == sc.FORMAT_VERSION
+        assert info["algorithm"] == "aes-256-gcm"
+        assert bytes(sc.decrypt_bytes(key, blob)) == b"def initialize(context): pass"
+
+    def test_wrong_key(self):
+        blob = sc.encrypt_bytes(new_key(), b"secret")
+        with pytest.raises(sc.AuthenticationError):
+            sc.decrypt_bytes(new_key(), blob)
+
+    def test_tamper(self):
+        key = new_key()
+        blob = sc.encrypt_bytes(key, b"secret strategy", key_id="k1")
+        header_len = sc.inspect(blob)["header_len"]
+        # 头部中的 key_id、nonce 之后的密文与末尾的 tag
+        for index in (header_len - 1, header_len + 12, len(blob) - 1):
+            with pytest.raises(sc.StrategyCryptoError):
+                sc.decrypt_bytes(key, flip(blob, index))
+
+    def test_truncation(self):
+        key = new_key()
+        blob = sc.encrypt_bytes(key, b"secret strategy")
+        header_len = sc.inspect(blob)["header_len"]
+        for length in (3, header_len - 1, header_len + 8, len(blob) - 1):
+            with pytest.raises(sc.StrategyCryptoError):
+                sc.decrypt_bytes(key, blob[:length])
+
+    def test_legacy_headerless_blob(self):
+        key = new_key()
+        blob = legacy_blob(key, b"legacy")
+        assert not sc.is_encrypted(blob)
+        assert bytes(sc.decrypt_bytes(key, blob)) == b"legacy"
+
+
+@pytest.mark.unit
+class TestContext:
+    """关联数据绑定"""
+
+    def test_match(self):
+        key = new_key()
+        blob = sc.encrypt_bytes_with_context(key, b"src", {"strategy_id": "s1", "version": "2"})
+        assert sc.inspect(blob)["context"] == {"strategy_id": "s1", "version": "2"}
+        plaintext = sc.decrypt_bytes_with_context(key, blob, {"strategy_id": "s1"})
+        assert bytes(plaintext) == b"src"
+
+    def test_mismatch(self):
+        key = new_key()
+        blob = sc.encrypt_bytes_with_context(key, b"src", {"strategy_id": "s1"})
+        with pytest.raises(sc.AuthenticationError):
+            sc.decrypt_bytes_with_context(key, blob, {"strategy_id": "s2"})
+        with pytest.raises(sc.AuthenticationError):
+            sc.decrypt_bytes_with_context(key, blob, {"strategy_id": "s1", "version": "1"})
+
+    def test_unbound_container_rejected(self):
+        key = new_key()
+        blob = sc.encrypt_bytes(key, b"src")
+        # allow_unbound 只放行没有头部的旧版密文
+        with pytest.raises(sc.AuthenticationError):
+            sc.decrypt_bytes_with_context(key, blob, {"strategy_id": "s1"}, allow_unbound=True)
+
+    def test_legacy_blob_requires_allow_unbound(self):
+        key = new_key()
+        blob = legacy_blob(key, b"legacy")
+        with pytest.raises(sc.AuthenticationError):
+            sc.decrypt_bytes_with_context(key, blob, {"strategy_id": "s1"})
+        plaintext = sc.decrypt_bytes_with_context(
+            key, blob, {"strategy_id": "s1"}, allow_unbound=True
+        )
+        assert bytes(plaintext) == b"legacy"
+
+
+@pytest.mark.unit
+class TestKeySelection:
+    """没有内置默认密钥，必须显式提供密钥"""
+
+    def test_missing_key(self):
+        blob = sc.encrypt_bytes(new_key(), b"src")
+        with pytest.raises(sc.KeyNotConfiguredError):
+            sc.decrypt_bytes("", blob)
+        with pytest.raises(sc.KeyNotConfiguredError):
+            sc.encrypt_bytes("", b"src")
+        assert issubclass(sc.KeyNotConfiguredError, sc.InvalidKeyError)
+
+    @pytest.mark.skipif(
+        not hasattr(sc, "migrate_default_key_blob"), reason="需要 legacy-default-key 特性"
+    )
+    def test_migrate_default_key_blob(self):
+        old = legacy_blob(
+            "b64:" + base64.b64encode(b"agfdsfsdafsdafdsafsdafdsafdfghdy").decode(), b"legacy"
+        )
+        key = new_key()
+        blob = sc.migrate_default_key_blob(old, key, key_id="k1")
+        assert sc.inspect(blob)["key_id"] == "k1"
+        assert bytes(sc.decrypt_bytes(key, blob)) == b"legacy"
+
+
+@pytest.mark.unit
+class TestKeyEncoding:
+    """带前缀的密钥字符串"""
+
+    def test_prefixes_decode_to_same_key(self, tmp_path):
+        raw = os.urandom(32)
+        text_file = tmp_path / "key.txt"
+        text_file.write_text("hex:" + raw.hex() + "\n")
+        raw_file = tmp_path / "key.bin"
+        raw_file.write_bytes(raw)
+        blob = sc.encrypt_bytes("b64:" + base64.b64encode(raw).decode(), b"src")
+        for key in ("hex:" + raw.hex(), f"file:{text_file}", f"file:{raw_file}"):
+            assert bytes(sc.decrypt_bytes(key, blob)) == b"src"
+        ascii_key = "k" * 32
+        blob = sc.encrypt_bytes("raw:" + ascii_key, b"src")
+        assert bytes(sc.decrypt_bytes(ascii_key, blob)) == b"src"
+
+    def test_invalid_key_reports_each_encoding(self):
+        with pytest.raises(sc.InvalidKeyError, match="hex"):
+            sc.encrypt_bytes("hex:abcd", b"src")
+        with pytest.raises(sc.InvalidKeyError) as excinfo:
+            sc.encrypt_bytes("not a key", b"src")
+        for encoding in ("raw", "hex", "b64"):
+            assert encoding in str(excinfo.value)
+
+    def test_strict_mode_requires_prefix(self):
+        raw = os.urandom(32)
+        sc.set_strict_keys(True)
+        try:
+            with pytest.raises(sc.InvalidKeyError, match="前缀"):
+                sc.encrypt_bytes(raw.hex(), b"src")
+            blob = sc.encrypt_bytes("hex:" + raw.hex(), b"src")
+        finally:
+            sc.set_strict_keys(False)
+        assert bytes(sc.decrypt_bytes(raw.hex(), blob)) == b"src"
+
+
+@pytest.mark.unit
+class TestExceptions:
+    """异常层级"""
+
+    def test_hierarchy(self):
+        assert issubclass(sc.StrategyCryptoError, ValueError)
+        for name in (
+            "InvalidKeyError",
+            "AuthenticationError",
+            "MalformedCiphertextError",
+            "UnsupportedVersionError",
+            "LicenseError",
+        ):
+            assert issubclass(getattr(sc, name), sc.StrategyCryptoError)
+
+    def test_malformed_and_unsupported(self):
+        key = new_key()
+        with pytest.raises(sc.MalformedCiphertextError):
+            sc.decrypt_bytes(key, b"short")
+        blob = bytearray(sc.encrypt_bytes(key, b"src"))
+        # 头部中 magic 之后的版本号
+        blob[4] = 0xFF
+        with pytest.raises(sc.UnsupportedVersionError):
+            sc.decrypt_bytes(key, bytes(blob))
+
+
+@pytest.mark.unit
+class TestProbe:
+    """无需密钥的头部探测"""
+
+    def test_inspect(self):
+        blob = sc.encrypt_bytes(new_key(), b"x" * 100, key_id="k1")
+        info = sc.inspect(blob)
+        assert info["key_id"] == "k1"
+        assert info["payload_len"] == 100
+        assert info["total_len"] == len(blob)
+        assert info["kdf"] is None and info["context"] is None
+
+    def test_plaintext_is_not_encrypted(self):
+        source = b"def initialize(context):\n    pass\n"
+        assert not sc.is_encrypted(source)
+        assert not sc.is_encrypted(b"")
+        with pytest.raises(sc.MalformedCiphertextError):
+            sc.inspect(source)
+
+
+@pytest.mark.unit
+class TestPassword:
+    """口令加密（Argon2id）"""
+
+    # 允许的最低成本，缩短测试时间
+    COST = {"m_cost": 19 * 1024, "t_cost": 2}
+
+    def test_round_trip(self):
+        blob = sc.encrypt_with_password("correct horse", b"src", **self.COST)
+        kdf = sc.inspect(blob)["kdf"]
+        assert kdf["algorithm"] == "Argon2id"
+        assert (kdf["m_cost"], kdf["t_cost"]) == (19 * 1024, 2)
+        assert bytes(sc.decrypt_with_password("correct horse", blob)) == b"src"
+        with pytest.raises(sc.AuthenticationError):
+            sc.decrypt_with_password("wrong", blob)
+
+    def test_context(self):
+        blob = sc.encrypt_with_password("pw", b"src", context={"strategy_id": "s1"}, **self.COST)
+        assert bytes(sc.decrypt_with_password("pw", blob, expected={"strategy_id": "s1"})) == b"src"
+        with pytest.raises(sc.AuthenticationError):
+            sc.decrypt_with_password("pw", blob, expected={"strategy_id": "s2"})
+
+    def test_key_and_password_blobs_not_interchangeable(self):
+        key = new_key()
+        with pytest.raises(sc.InvalidKeyError):
+            sc.decrypt_with_password("pw", sc.encrypt_bytes(key, b"src"))
+        with pytest.raises(sc.StrategyCryptoError):
+            sc.decrypt_bytes(key, sc.encrypt_with_password("pw", b"src", **self.COST))
+
+    def test_cost_below_minimum_rejected(self):
+        with pytest.raises(ValueError):
+            sc.encrypt_with_password("pw", b"src", m_cost=1024, t_cost=1)
+
+
+@pytest.mark.unit
+class TestDerivedKeys:
+    """由主密钥派生的策略子密钥（HKDF-SHA256）"""
+
+    def test_derive_key(self):
+        master = new_key()
+        key = sc.derive_key(master, {"strategy_id": "s1"})
+        assert key.startswith("b64:")
+        assert key == sc.derive_key(master, {"strategy_id": "s1"})
+        assert key != sc.derive_key(master, {"strategy_id": "s2"})
+        assert key != sc.derive_key(master, {"strategy_id": "s1", "version": "2"})
+        assert key != sc.derive_key(new_key(), {"strategy_id": "s1"})
+        with pytest.raises(ValueError, match="strategy_id"):
+            sc.derive_key(master, {"version": "1"})
+
+    def test_round_trip(self):
+        master = new_key()
+        context = {"strategy_id": "s1", "version": "3"}
+        blob = sc.encrypt_bytes_derived(master, b"src", context)
+        assert sc.inspect(blob)["context"] == context
+        assert bytes(sc.decrypt_bytes_derived(master, blob)) == b"src"
+        assert bytes(sc.decrypt_bytes_derived(master, blob, expected=context)) == b"src"
+        # 子密钥可以单独下发给客户端，用普通解密接口解密
+        assert bytes(sc.decrypt_bytes(sc.derive_key(master, context), blob)) == b"src"
+        with pytest.raises(sc.AuthenticationError):
+            sc.decrypt_bytes_derived(master, blob, expected={"strategy_id": "s2"})
+        with pytest.raises(sc.AuthenticationError):
+            sc.decrypt_bytes_derived(new_key(), blob)
+
+
+@pytest.mark.unit
+class TestKeyring:
+    """密钥环与轮换"""
+
+    def test_encrypt_with_primary_and_decrypt_by_key_id(self):
+        old, new = new_key(), new_key()
+        ring = sc.Keyring()
+        ring.add("old", old, primary=True)
+        old_blob = ring.encrypt(b"v1")
+        ring.add("new", new, primary=True)
+        new_blob = ring.encrypt(b"v2", context={"strategy_id": "s1"})
+        assert ring.primary == "new"
+        assert ring.key_ids() == ["new", "old"]
+        assert sc.inspect(old_blob)["key_id"] == "old"
+        assert sc.inspect(new_blob)["key_id"] == "new"
+        assert bytes(ring.decrypt(old_blob)) == b"v1"
+        assert bytes(ring.decrypt(new_blob, expected={"strategy_id": "s1"})) == b"v2"
+        ring.remove("old")
+        with pytest.raises(sc.StrategyCryptoError):
+            ring.decrypt(old_blob)
+
+    def test_trial_decryption_without_key_id(self):
+        key = new_key()
+        ring = sc.Keyring()
+        ring.add("a", new_key(), primary=True)
+        ring.add("b", key)
+        assert bytes(ring.decrypt(legacy_blob(key, b"legacy"))) == b"legacy"
+        with pytest.raises(sc.AuthenticationError):
+            ring.decrypt(legacy_blob(new_key(), b"legacy"))
+
+    def test_from_env(self, monkeypatch):
+        k1, k2 = new_key(), new_key()
+        monkeypatch.setenv("STRATEGY_KEYS", f"k1={k1}; k2@chacha20-poly1305={k2}")
+        ring = sc.Keyring.from_env()
+        assert ring.key_ids() == ["k1", "k2"]
+        assert dict(ring.algorithms()) == {"k1": None, "k2": "chacha20-poly1305"}
+        blob = ring.encrypt(b"src", key_id="k2")
+        assert sc.inspect(blob)["algorithm"] == "chacha20-poly1305"
+        assert bytes(sc.decrypt_bytes(k2, blob)) == b"src"
+        monkeypatch.setenv("STRATEGY_KEYS", " ")
+        with pytest.raises(sc.KeyNotConfiguredError):
+            sc.Keyring.from_env()
+
+    def test_from_file(self, tmp_path):
+        key = new_key()
+        path = tmp_path / "keys"
+        path.write_text(f"# 密钥环\nk1={key}\n\n")
+        ring = sc.Keyring.from_file(str(path))
+        assert "k1" in ring and len(ring) == 1
+        path.write_text(f"k1={key}\nk1={new_key()}\n")
+        with pytest.raises(ValueError, match="重复"):
+            sc.Keyring.from_file(str(path))
+        path.write_text("hex:" + os.urandom(32).hex())
+        with pytest.raises(ValueError, match="格式错误"):
+            sc.Keyring.from_file(str(path))
+
+
+@pytest.mark.unit
+class TestReencrypt:
+    """换钥：明文不经过 Python"""
+
+    def test_reencrypt_keeps_context(self):
+        old, new = new_key(), new_key()
+        blob = sc.encrypt_bytes_with_context(old, b"src", {"strategy_id": "s1"}, key_id="old")
+        out = sc.reencrypt(blob, old, new, key_id="new", algorithm="xchacha20-poly1305")
+        info = sc.inspect(out)
+        assert info["key_id"] == "new"
+        assert info["algorithm"] == "xchacha20-poly1305"
+        assert bytes(sc.decrypt_bytes_with_context(new, out, {"strategy_id": "s1"})) == b"src"
+        with pytest.raises(sc.AuthenticationError):
+            sc.reencrypt(blob, new_key(), new)
+
+    def test_reencrypt_legacy_blob(self):
+        old, new = new_key(), new_key()
+        out = sc.reencrypt(legacy_blob(old, b"legacy"), old, new)
+        assert sc.is_encrypted(out)
+        assert bytes(sc.decrypt_bytes(new, out)) == b"legacy"
+
+    def test_reencrypt_dir(self, tmp_path):
+        old, new = new_key(), new_key()
+        (tmp_path / "sub").mkdir()
+        top = tmp_path / "a.enc"
+        nested = tmp_path / "sub" / "b.enc"
+        other = tmp_path / "c.txt"
+        broken = tmp_path / "d.enc"
+        for path in (top, nested, other):
+            path.write_bytes(sc.encrypt_bytes(old, path.name.encode()))
+        broken.write_bytes(b"not a ciphertext")
+
+        result = sc.reencrypt_dir(tmp_path, old, new, suffix=".enc")
+        assert sorted(os.path.basename(p) for p in result["succeeded"]) == ["a.enc"]
+        assert [os.path.basename(p) for p, _ in result["failed"]] == ["d.enc"]
+        assert bytes(sc.decrypt_bytes(new, top.read_bytes())) == b"a.enc"
+        assert bytes(sc.decrypt_bytes(old, nested.read_bytes())) == b"b.enc"
+        assert bytes(sc.decrypt_bytes(old, other.read_bytes())) == b"c.txt"
+        assert broken.read_bytes() == b"not a ciphertext"
+
+        result = sc.reencrypt_dir(tmp_path / "sub", old, new, recursive=True)
+        assert len(result["succeeded"]) == 1
+        assert bytes(sc.decrypt_bytes(new, nested.read_bytes())) == b"b.enc"
+        assert not list(tmp_path.rglob("*.tmp"))
+
+    @pytest.mark.skipif(not hasattr(os, "symlink"), reason="需要符号链接")
+    def test_reencrypt_dir_skips_symlinked_directories(self, tmp_path):
+        old, new = new_key(), new_key()
+        outside = tmp_path / "outside"
+        outside.mkdir()
+        target = outside / "x.enc"
+        target.write_bytes(sc.encrypt_bytes(old, b"x"))
+        root = tmp_path / "root"
+        root.mkdir()
+        try:
+            os.symlink(outside, root / "link")
+        except OSError:
+            pytest.skip("无法创建符号链接")
+        result = sc.reencrypt_dir(root, old, new, recursive=True)
+        assert result["succeeded"] == [] and result["failed"] == []
+        assert bytes(sc.decrypt_bytes(old, target.read_bytes())) == b"x"
+
+
+@pytest.mark.unit
+class TestSecretBuffers:
+    """密钥与明文缓冲区"""
+
+    def test_mlock_round_trip(self):
+        key = new_key()
+        sc.set_mlock(True)
+        try:
+            blob = sc.encrypt_bytes(key, b"x" * 100_000)
+            assert bytes(sc.decrypt_bytes(key, blob)) == b"x" * 100_000
+        finally:
+            sc.set_mlock(False)
+
+
+@pytest.mark.unit
+class TestAlgorithms:
+    """可选的 AEAD 算法"""
+
+    @pytest.mark.parametrize("algorithm", ["chacha20-poly1305", "xchacha20-poly1305"])
+    def test_chacha_round_trip(self, algorithm):
+        assert algorithm in sc.ALGORITHMS
+        key = new_key()
+        blob = sc.encrypt_bytes(key, b"src", algorithm=algorithm)
+        assert sc.inspect(blob)["algorithm"] == algorithm
+        assert bytes(sc.decrypt_bytes(key, blob)) == b"src"
+        with pytest.raises(sc.AuthenticationError):
+            sc.decrypt_bytes(key, flip(blob, len(blob) - 1))
+
+    def test_unknown_algorithm(self):
+        with pytest.raises(ValueError):
+            sc.encrypt_bytes(new_key(), b"src", algorithm="des")
+
+    def test_gcm_siv_round_trip(self):
+        key = new_key()
+        blob = sc.encrypt_bytes(key, b"src", algorithm="aes-256-gcm-siv")
+        assert sc.inspect(blob)["algorithm"] == "aes-256-gcm-siv"
+        assert bytes(sc.decrypt_bytes(key, blob)) == b"src"
+
+    def test_keyring_entry_algorithm(self):
+        key = new_key()
+        ring = sc.Keyring()
+        ring.add("siv", key, primary=True, algorithm="aes-256-gcm-siv")
+        blob = ring.encrypt(b"src")
+        assert sc.inspect(blob)["algorithm"] == "aes-256-gcm-siv"
+        # 调用时指定的算法优先于密钥条目的默认算法
+        blob = ring.encrypt(b"src", algorithm="aes-256-gcm")
+        assert sc.inspect(blob)["algorithm"] == "aes-256-gcm"
+        assert bytes(ring.decrypt(blob)) == b"src"
+
+
+@pytest.mark.unit
+class TestKeyCommitment:
+    """密钥承诺"""
+
+    @pytest.mark.parametrize("algorithm", list(sc.ALGORITHMS))
+    def test_all_ciphertexts_committed(self, algorithm):
+        key = new_key()
+        blob = sc.encrypt_bytes(key, b"src", algorithm=algorithm)
+        assert sc.inspect(blob)["key_committed"] is True
+        with pytest.raises(sc.AuthenticationError):
+            sc.decrypt_bytes(new_key(), blob)
+
+    def test_container_without_commitment_rejected(self):
+        aead = pytest.importorskip("cryptography.hazmat.primitives.ciphers.aead")
+        raw_key = os.urandom(32)
+        # magic | version | alg | flags=0 | key_id_len=0 | ext_len=0
+        header = b"BTSC" + bytes([1, 1, 0, 0, 0, 0, 0])
+        nonce = os.urandom(12)
+        blob = header + nonce + aead.AESGCM(raw_key).encrypt(nonce, b"x", header)
+        key = "b64:" + base64.b64encode(raw_key).decode()
+        with pytest.raises(sc.MalformedCiphertextError):
+            sc.decrypt_bytes(key, blob)
+
+
+@pytest.mark.unit
+class TestStream:
+    """分块流格式"""
+
+    CHUNK = 1024
+
+    def encrypt(self, key: str, data: bytes, **kwargs) -> bytes:
+        out = io.BytesIO()
+        with sc.EncryptWriter(out, key, chunk_size=self.CHUNK, **kwargs) as writer:
+            writer.write(data)
+        return out.getvalue()
+
+    def chunk_offsets(self, blob: bytes) -> tuple[int, int]:
+        """第一块的起始位置与每块的密文长度"""
+        return sc.inspect(blob)["header_len"] + 32, self.CHUNK + 16
+
+    @pytest.mark.parametrize("size", [0, 1, 1024, 1025, 5000])
+    def test_round_trip(self, size):
+        key = new_key()
+        data = os.urandom(size)
+        blob = self.encrypt(key, data)
+        assert sc.inspect(blob)["chunk_size"] == self.CHUNK
+        with sc.DecryptReader(io.BytesIO(blob), key) as reader:
+            assert reader.read() == data
+
+    def test_wrong_key(self):
+        blob = self.encrypt(new_key(), b"data")
+        with pytest.raises(sc.AuthenticationError):
+            sc.DecryptReader(io.BytesIO(blob), new_key())
+
+    def test_truncated_at_chunk_boundary(self):
+        key = new_key()
+        data = os.urandom(3 * self.CHUNK + 100)
+        blob = self.encrypt(key, data)
+        # 去掉完整的最后一块，剩下的密文恰好在块边界结束
+        truncated = blob[: len(blob) - (100 + 16)]
+        reader = sc.DecryptReader(io.BytesIO(truncated), key)
+        with pytest.raises(sc.StrategyCryptoError):
+            reader.read()
+
+    def test_reordered_chunks(self):
+        key = new_key()
+        data = os.urandom(3 * self.CHUNK + 100)
+        blob = self.encrypt(key, data)
+        start, chunk_len = self.chunk_offsets(blob)
+        first = blob[start : start + chunk_len]
+        second = blob[start + chunk_len : start + 2 * chunk_len]
+        reordered = blob[:start] + second + first + blob[start + 2 * chunk_len :]
+        with pytest.raises(sc.AuthenticationError):
+            sc.DecryptReader(io.BytesIO(reordered), key).read()
+
+    def test_tampered_chunk(self):
+        key = new_key()
+        data = os.urandom(3 * self.CHUNK)
+        blob = self.encrypt(key, data)
+        start, chunk_len = self.chunk_offsets(blob)
+        reader = sc.DecryptReader(io.BytesIO(flip(blob, start + chunk_len + 5)), key)
+        assert reader.read(self.CHUNK) == data[: self.CHUNK]
+        with pytest.raises(sc.AuthenticationError):
+            reader.read()
+
+    def test_context(self):
+        key = new_key()
+        blob = self.encrypt(key, b"data", context={"strategy_id": "s1"})
+        reader = sc.DecryptReader(io.BytesIO(blob), key, expected={"strategy_id": "s1"})
+        assert reader.read() == b"data"
+        with pytest.raises(sc.AuthenticationError):
+            sc.DecryptReader(io.BytesIO(blob), key, expected={"strategy_id": "s2"})
+
+    def test_seek_and_read_at(self):
+        key = new_key()
+        data = os.urandom(5 * self.CHUNK + 123)
+        blob = self.encrypt(key, data)
+        reader = sc.DecryptReader(io.BytesIO(blob), key)
+        assert reader.seekable()
+        assert reader.size() == len(data)
+        assert reader.seek(1500) == 1500
+        assert reader.read(100) == data[1500:1600]
+        assert reader.read_at(3000, 2000) == data[3000:5000]
+        assert reader.tell() == 1600
+        reader.seek(-10, 2)
+        assert reader.read() == data[-10:]
+        assert reader.read_at(len(data) + 10, 5) == b""
+
+    def test_non_seekable_source(self):
+        class Unseekable(io.RawIOBase):
+            def __init__(self, raw: bytes):
+                self._inner = io.BytesIO(raw)
+
+            def readable(self):
+                return True
+
+            def readinto(self, buf):
+                chunk = self._inner.read(len(buf))
+                buf[: len(chunk)] = chunk
+                return len(chunk)
+
+        key = new_key()
+        data = os.urandom(4 * self.CHUNK + 7)
+        reader = sc.DecryptReader(Unseekable(self.encrypt(key, data)), key)
+        assert reader.read(10) + reader.read() == data
+
+
+@pytest.mark.unit
+class TestCompression:
+    """加密前压缩"""
+
+    def test_round_trip(self):
+        key = new_key()
+        data = b"x = 1\n" * 1000
+        blob = sc.encrypt_bytes(key, data, compression="zstd")
+        assert sc.inspect(blob)["compression"] == {"codec": "zstd", "original_len": len(data)}
+        assert len(blob) < len(data) // 10
+        assert bytes(sc.decrypt_bytes(key, blob)) == data
+        assert sc.inspect(sc.encrypt_bytes(key, data))["compression"] is None
+        with pytest.raises(ValueError):
+            sc.encrypt_bytes(key, data, compression="gzip")
+
+    def test_max_decompressed_size(self):
+        key = new_key()
+        data = b"\0" * 100_000
+        blob = sc.encrypt_bytes(key, data, compression="zstd")
+        previous = sc.set_max_decompressed_size(len(data) - 1)
+        try:
+            # 头部记录的原始长度超过上限时不做任何解压
+            with pytest.raises(sc.MalformedCiphertextError, match="上限"):
+                sc.decrypt_bytes(key, blob)
+            sc.set_max_decompressed_size(len(data))
+            assert bytes(sc.decrypt_bytes(key, blob)) == data
+        finally:
+            sc.set_max_decompressed_size(previous)
+
+
+@pytest.mark.unit
+class TestPadding:
+    """隐藏明文长度的填充"""
+
+    def test_block_padding_hides_length(self):
+        key = new_key()
+        short = sc.encrypt_bytes(key, b"x", padding="block:4096")
+        long = sc.encrypt_bytes(key, b"x" * 3000, padding="block:4096")
+        assert len(short) == len(long)
+        assert sc.inspect(short)["padding"] == "block:4096"
+        assert bytes(sc.decrypt_bytes(key, short)) == b"x"
+        assert bytes(sc.decrypt_bytes(key, long)) == b"x" * 3000
+
+    def test_pow2_padding(self):
+        key = new_key()
+        sizes = {len(sc.encrypt_bytes(key, b"x" * n, padding="pow2")) for n in (600, 700, 1000)}
+        assert len(sizes) == 1
+        blob = sc.encrypt_bytes(key, b"x" * 700, padding="pow2")
+        assert bytes(sc.decrypt_bytes(key, blob)) == b"x" * 700
+
+    def test_invalid_spec(self):
+        with pytest.raises(ValueError):
+            sc.encrypt_bytes(new_key(), b"x", padding="block:0")
+        with pytest.raises(ValueError):
+            sc.encrypt_bytes(new_key(), b"x", padding="random")
+
+
+@pytest.mark.unit
+class TestBundle:
+    """多文件策略包"""
+
+    @pytest.fixture
+    def strategy_dir(self, tmp_path):
+        root = tmp_path / "strategy"
+        (root / "pkg").mkdir(parents=True)
+        (root / "main.py").write_text("from pkg import helper\nVALUE = helper.VALUE\n")
+        (root / "pkg" / "__init__.py").write_text("")
+        (root / "pkg" / "helper.py").write_text("VALUE = 42\n")
+        (root / "params.json").write_text("{}")
+        (root / "__pycache__").mkdir()
+        (root / "__pycache__" / "main.cpython-311.pyc").write_bytes(b"\0")
+        return root
+
+    def pack(self, strategy_dir, key: str, **kwargs) -> bytes:
+        return sc.pack_bundle(strategy_dir, key, entry_point="main", **kwargs)
+
+    def test_round_trip(self, strategy_dir):
+        key = new_key()
+        blob = self.pack(strategy_dir, key, context={"strategy_id": "s1", "version": "1"})
+        assert sc.is_bundle(blob)
+        bundle = sc.Bundle(blob, key)
+        assert bundle.entry_point == "main"
+        assert bundle.context == {"strategy_id": "s1", "version": "1"}
+        paths = sorted(entry["path"] for entry in bundle.entries())
+        assert paths == ["main.py", "params.json", "pkg/__init__.py", "pkg/helper.py"]
+        assert bundle.read("pkg/helper.py") == b"VALUE = 42\n"
+
+    def test_context(self, strategy_dir):
+        key = new_key()
+        blob = self.pack(strategy_dir, key, context={"strategy_id": "s1"})
+        assert sc.Bundle(blob, key, context={"strategy_id": "s1"}).context["strategy_id"] == "s1"
+        with pytest.raises(sc.AuthenticationError):
+            sc.Bundle(blob, key, context={"strategy_id": "s2"})
+        with pytest.raises(sc.AuthenticationError):
+            sc.Bundle(self.pack(strategy_dir, key), key, context={"strategy_id": "s1"})
+        with pytest.raises(ValueError):
+            self.pack(strategy_dir, key, context={"path": "main.py"})
+
+    def test_wrong_key_and_tamper(self, strategy_dir):
+        key = new_key()
+        blob = self.pack(strategy_dir, key)
+        with pytest.raises(sc.AuthenticationError):
+            sc.Bundle(blob, new_key())
+        # 条目区在末尾，篡改最后一个条目只在读取该条目时失败
+        bundle = sc.Bundle(flip(blob, len(blob) - 1), key)
+        last = sorted(entry["path"] for entry in bundle.entries())[-1]
+        with pytest.raises(sc.AuthenticationError):
+            bundle.read(last)
+
+    @pytest.mark.skipif(not hasattr(os, "symlink"), reason="需要符号链接")
+    def test_symlinked_directory_skipped(self, strategy_dir):
+        try:
+            os.symlink(strategy_dir, strategy_dir / "pkg" / "loop")
+        except OSError:
+            pytest.skip("无法创建符号链接")
+        key = new_key()
+        bundle = sc.Bundle(self.pack(strategy_dir, key), key)
+        assert not any(entry["path"].startswith("pkg/loop/") for entry in bundle.entries())
+
+    def test_importer(self, strategy_dir):
+        key = new_key()
+        importer = sc.BundleImporter(sc.Bundle(self.pack(strategy_dir, key), key))
+        importer.install()
+        try:
+            module = importlib.import_module("main")
+            assert module.VALUE == 42
+            assert module.__spec__.origin == "bundle://main.py"
+        finally:
+            importer.uninstall()
+            for name in ("main", "pkg", "pkg.helper"):
+                sys.modules.pop(name, None)
+
+
+@pytest.mark.unit
+class TestSignature:
+    """发布者签名"""
+
+    def test_trusted_publisher(self):
+        key = new_key()
+        signing_key, public_key = sc.generate_publisher_key()
+        blob = sc.encrypt_bytes(key, b"src", signing_key=signing_key)
+        assert sc.inspect(blob)["signer"] == public_key
+        sc.set_trusted_publishers([public_key])
+        assert bytes(sc.decrypt_bytes(key, blob)) == b"src"
+
+    def test_untrusted_or_missing_signature(self):
+        key = new_key()
+        signing_key, _ = sc.generate_publisher_key()
+        _, trusted = sc.generate_publisher_key()
+        signed = sc.encrypt_bytes(key, b"src", signing_key=signing_key)
+        unsigned = sc.encrypt_bytes(key, b"src")
+        sc.set_trusted_publishers([trusted])
+        for blob in (signed, unsigned, legacy_blob(key, b"src")):
+            with pytest.raises(sc.SignatureError):
+                sc.decrypt_bytes(key, blob)
+
+    def test_tampered_signature(self):
+        key = new_key()
+        signing_key, public_key = sc.generate_publisher_key()
+        blob = sc.encrypt_bytes(key, b"src", signing_key=signing_key)
+        sc.set_trusted_publishers([public_key])
+        with pytest.raises(sc.SignatureError):
+            sc.decrypt_bytes(key, flip(blob, len(blob) - 1))
+
+    def test_keyring_stops_on_signature_error(self):
+        signing_key, _ = sc.generate_publisher_key()
+        _, trusted = sc.generate_publisher_key()
+        key = new_key()
+        ring = sc.Keyring()
+        ring.add("k1", key, primary=True)
+        ring.add("k2", new_key())
+        blob = sc.encrypt_bytes(key, b"src", signing_key=signing_key)
+        sc.set_trusted_publishers([trusted])
+        with pytest.raises(sc.SignatureError):
+            ring.decrypt(blob)
+
+    def test_signed_stream(self):
+        key = new_key()
+        signing_key, public_key = sc.generate_publisher_key()
+        data = os.urandom(3000)
+        out = io.BytesIO()
+        with sc.EncryptWriter(out, key, chunk_size=1024, signing_key=signing_key) as writer:
+            writer.write(data)
+        blob = out.getvalue()
+        assert sc.inspect(blob)["signer"] == public_key
+        assert sc.inspect(blob)["payload_len"] == len(data)
+        sc.set_trusted_publishers([public_key])
+        reader = sc.DecryptReader(io.BytesIO(blob), key)
+        assert reader.read_at(2000, 500) == data[2000:2500]
+        assert reader.read() == data
+        # 每块都带签名，篡改任意一块的签名在读取该块时失败
+        reader = sc.DecryptReader(io.BytesIO(flip(blob, len(blob) - 1)), key)
+        with pytest.raises(sc.SignatureError):
+            reader.read()
+
+    def test_stream_pinning(self):
+        key = new_key()
+        signing_key, _ = sc.generate_publisher_key()
+        _, trusted = sc.generate_publisher_key()
+        signed, unsigned = io.BytesIO(), io.BytesIO()
+        with sc.EncryptWriter(signed, key, signing_key=signing_key) as writer:
+            writer.write(b"data")
+        with sc.EncryptWriter(unsigned, key) as writer:
+            writer.write(b"data")
+        sc.set_trusted_publishers([trusted])
+        for out in (signed, unsigned):
+            with pytest.raises(sc.SignatureError):
+                sc.DecryptReader(io.BytesIO(out.getvalue()), key)
+
+
+@pytest.mark.unit
+class TestLicense:
+    """授权凭证"""
+
+    def issue(self, signing_key, **kwargs):
+        kwargs.setdefault("expires_at", int(time.time()) + 3600)
+        return sc.issue_license(signing_key, "alice", ["s1"], ["backtest", "paper"], **kwargs)
+
+    def test_check(self, authority, machine):
+        license = sc.License(self.issue(authority, machine=machine))
+        assert license.licensee == "alice"
+        assert license.machine == machine
+        assert license.modes == ["backtest", "paper"]
+        license.check("s1", "backtest")
+        with pytest.raises(sc.LicenseError):
+            license.check("s2", "backtest")
+        with pytest.raises(sc.LicenseError):
+            license.check("s1", "live")
+
+    def test_expired_and_not_yet_valid(self, authority):
+        now = int(time.time())
+        with pytest.raises(sc.LicenseError):
+            sc.License(self.issue(authority, expires_at=now - 1)).check("s1", "backtest")
+        license = sc.License(self.issue(authority, not_before=now + 600))
+        with pytest.raises(sc.LicenseError):
+            license.check("s1", "backtest")
+
+    def test_other_machine(self, authority):
+        license = sc.License(self.issue(authority, machine="0" * 32))
+        with pytest.raises(sc.LicenseError):
+            license.check("s1", "backtest")
+
+    def test_untrusted_authority(self, authority):
+        other, _ = sc.generate_publisher_key()
+        with pytest.raises(sc.LicenseError):
+            sc.License(self.issue(other))
+        token = self.issue(authority)
+        with pytest.raises(sc.LicenseError):
+            sc.License(token[:-2] + ("A" if token[-2] != "A" else "B") + token[-1])
+
+    def test_decrypt_licensed(self, authority):
+        key = new_key()
+        license = sc.License(self.issue(authority))
+        blob = sc.encrypt_bytes_with_context(key, b"src", {"strategy_id": "s1"})
+        plaintext = sc.decrypt_licensed(key, blob, license, "s1", "backtest")
+        assert bytes(plaintext) == b"src"
+        with pytest.raises(sc.LicenseError):
+            sc.decrypt_licensed(key, blob, license, "s1", "live")
+        other = sc.encrypt_bytes_with_context(key, b"src", {"strategy_id": "s2"})
+        with pytest.raises(sc.StrategyCryptoError):
+            sc.decrypt_licensed(key, other, license, "s1", "backtest")
+
+    def test_unlicensed_decryption_refused(self, authority, tmp_path):
+        key = new_key()
+        context = {"strategy_id": "s1"}
+        blob = sc.encrypt_bytes_with_context(key, b"src", context)
+        ring = sc.Keyring()
+        ring.add("k1", key, primary=True)
+        ring_blob = ring.encrypt(b"src", context=context)
+        stream = io.BytesIO()
+        with sc.EncryptWriter(stream, key, context=context) as writer:
+            writer.write(b"src")
+        (tmp_path / "main.py").write_text("")
+        bundle = sc.pack_bundle(tmp_path, key, context=context)
+        # 配置授权公钥后，不带授权的解密接口一律拒绝
+        for decrypt in (
+            lambda: sc.decrypt_bytes(key, blob),
+            lambda: sc.decrypt_bytes_with_context(key, blob, context),
+            lambda: ring.decrypt(ring_blob),
+            lambda: sc.DecryptReader(io.BytesIO(stream.getvalue()), key),
+            lambda: sc.Bundle(bundle, key),
+        ):
+            with pytest.raises(sc.LicenseError, match="授权"):
+                decrypt()
+
+        license = sc.License(self.issue(authority))
+        licensed = {"license": license, "mode": "backtest"}
+        assert bytes(ring.decrypt(ring_blob, **licensed)) == b"src"
+        assert sc.DecryptReader(io.BytesIO(stream.getvalue()), key, **licensed).read() == b"src"
+        assert sc.Bundle(bundle, key, **licensed).context == context
+        # 授权按密文绑定的策略 ID 与运行模式校验
+        with pytest.raises(sc.LicenseError):
+            sc.Bundle(bundle, key, license=license, mode="live")
+        with pytest.raises(sc.LicenseError, match="strategy_id"):
+            ring.decrypt(ring.encrypt(b"src"), **licensed)
+
+
+@pytest.mark.unit
+class TestOfflineGrace:
+    """离线宽限期"""
+
+    def time_token(self, authority, machine, **kwargs):
+        """服务端对本进程新挑战签发的时间凭证"""
+        return sc.issue_time_token(authority, machine, sc.time_challenge(), **kwargs)
+
+    def test_requires_online_confirmation(self, tmp_path):
+        grace = sc.OfflineGrace(tmp_path / "grace.state")
+        assert grace.remaining() is None
+        with pytest.raises(sc.LicenseError):
+            grace.check()
+
+    def test_grace_period_and_rollback(self, tmp_path, authority, machine):
+        now = int(time.time())
+        grace = sc.OfflineGrace(tmp_path / "grace.state", grace_seconds=3600)
+        grace.record_online(self.time_token(authority, machine, issued_at=now))
+        assert grace.last_online == now
+        assert grace.check(now=now + 600) == 3000
+        assert grace.last_seen == now + 600
+        with pytest.raises(sc.ClockRollbackError):
+            grace.check(now=now - 3600)
+        with pytest.raises(sc.LicenseError):
+            grace.check(now=now + 3601)
+
+    def test_time_token_cannot_be_replayed(self, tmp_path, authority, machine):
+        path = tmp_path / "grace.state"
+        grace = sc.OfflineGrace(path)
+        token = self.time_token(authority, machine)
+        grace.record_online(token)
+        with pytest.raises(sc.LicenseError, match="重放"):
+            grace.record_online(token)
+        # 删除状态文件后同样不能用旧凭证重新确认联网
+        path.unlink()
+        with pytest.raises(sc.LicenseError, match="重放"):
+            grace.record_online(token)
+        unknown = base64.urlsafe_b64encode(os.urandom(16)).decode().rstrip("=")
+        with pytest.raises(sc.LicenseError, match="重放"):
+            grace.record_online(sc.issue_time_token(authority, machine, unknown))
+        assert grace.last_online is None
+
+    def test_fresh_server_time_recovers_from_forward_clock(self, tmp_path, authority, machine):
+        now = int(time.time())
+        grace = sc.OfflineGrace(tmp_path / "grace.state")
+        grace.record_online(self.time_token(authority, machine, issued_at=now))
+        # 本机时钟曾被调快一天，之后校准回来会被判定为回拨
+        grace.check(now=now + 86400)
+        with pytest.raises(sc.ClockRollbackError):
+            grace.check(now=now + 60)
+        # 新鲜的服务端时间直接覆盖状态，不因早于见过的最晚时间而被拒绝
+        grace.record_online(self.time_token(authority, machine, issued_at=now + 60))
+        assert grace.last_online == now + 60
+        assert grace.last_seen == now + 60
+        assert grace.check(now=now + 120) > 0
+
+    def test_time_token_must_be_trusted_and_for_this_machine(self, tmp_path, authority, machine):
+        grace = sc.OfflineGrace(tmp_path / "grace.state")
+        other, _ = sc.generate_publisher_key()
+        with pytest.raises(sc.LicenseError):
+            grace.record_online(self.time_token(other, machine))
+        with pytest.raises(sc.LicenseError):
+            grace.record_online(self.time_token(authority, "0" * 32))
+        with pytest.raises(ValueError):
+            sc.issue_time_token(authority, machine, "not a challenge")
+        assert grace.last_online is None
+
+    def test_state_file_is_authenticated(self, tmp_path, authority, machine):
+        path = tmp_path / "grace.state"
+        grace = sc.OfflineGrace(path)
+        grace.record_online(self.time_token(authority, machine))
+        path.write_bytes(flip(path.read_bytes(), len(path.read_bytes()) - 1))
+        with pytest.raises(sc.LicenseError):
+            grace.check()
+        # 安装密钥缺失时同样无法读取，重新联网确认后恢复
+        grace.record_online(self.time_token(authority, machine))
+        os.remove(str(path) + ".key")
+        with pytest.raises(sc.LicenseError):
+            grace.check()
+        grace.record_online(self.time_token(authority, machine))
+        assert grace.check() > 0
+
+    def test_license_uses_trusted_time(self, tmp_path, authority, machine):
+        now = int(time.time())
+        grace = sc.OfflineGrace(tmp_path / "grace.state")
+        grace.record_online(self.time_token(authority, machine, issued_at=now))
+        token = sc.issue_license(authority, "alice", ["s1"], ["backtest"], now + 100)
+        license = sc.License(token)
+        license.check("s1", "backtest", now=now + 50, grace=grace)
+        grace.check(now=now + 200)
+        # 时钟拨回容差之内仍按见过的最晚时间判断，授权已过期
+        with pytest.raises(sc.LicenseError):
+            license.check("s1", "backtest", now=now + 50, grace=grace)
+
+
+@pytest.mark.unit
+class TestRevocation:
+    """吊销列表；列表为进程级状态且只能前进，每个用例使用唯一的策略 ID 与递增序号"""
+
+    def test_revoked_strategy_and_key(self, tmp_path, authority):
+        key = new_key()
+        revoked = f"revoked-{uuid.uuid4().hex}"
+        kept = f"kept-{uuid.uuid4().hex}"
+        key_id = f"key-{uuid.uuid4().hex}"
+        sequence = time.time_ns()
+        token = sc.issue_revocation_list(
+            authority, sequence, strategies=[f"{revoked}@2"], key_ids=[key_id]
+        )
+        cache = tmp_path / "revocations.token"
+        assert sc.set_revocation_list(token, cache_path=cache) == sequence
+        assert cache.exists()
+        assert sc.revocation_list()["strategies"] == [f"{revoked}@2"]
+        # 吊销列表加载后一直生效，不要求授权的解密同样检查
+        sc.set_license_authorities([])
+
+        v2 = sc.encrypt_bytes_with_context(key, b"src", {"strategy_id": revoked, "version": "2"})
+        v3 = sc.encrypt_bytes_with_context(key, b"src", {"strategy_id": revoked, "version": "3"})
+        with pytest.raises(sc.RevocationError):
+            sc.decrypt_bytes(key, v2)
+        assert bytes(sc.decrypt_bytes(key, v3)) == b"src"
+        other = sc.encrypt_bytes_with_context(key, b"src", {"strategy_id": kept})
+        assert bytes(sc.decrypt_bytes(key, other)) == b"src"
+        with pytest.raises(sc.RevocationError):
+            sc.decrypt_bytes(key, sc.encrypt_bytes(key, b"src", key_id=key_id))
+
+        # 旧序号的列表不能覆盖已见过的列表
+        sc.set_license_authorities([sc.publisher_public_key(authority)])
+        older = sc.issue_revocation_list(authority, sequence - 1)
+        with pytest.raises(sc.RevocationError):
+            sc.set_revocation_list(older, cache_path=cache)
+        assert sc.load_cached_revocation_list(cache) == sequence
+
+    def test_revoked_license(self, authority):
+        token = sc.issue_license(authority, "bob", ["s1"], ["live"], int(time.time()) + 3600)
+        license = sc.License(token)
+        sc.set_revocation_list(
+            sc.issue_revocation_list(authority, time.time_ns(), license_ids=[license.license_id])
+        )
+        with pytest.raises(sc.RevocationError):
+            license.check("s1", "live")
+
+    def test_unsigned_list_rejected(self, authority):
+        other, _ = sc.generate_publisher_key()
+        with pytest.raises(sc.LicenseError):
+            sc.set_revocation_list(sc.issue_revocation_list(other, time.time_ns()))
+
+
+@pytest.mark.unit
+class TestSealedKeys:
+    """封装给设备公钥的策略密钥"""
+
+    def test_round_trip(self):
+        key = new_key()
+        private_key, public_key = sc.generate_device_key()
+        assert sc.device_public_key(private_key) == public_key
+        sealed = sc.wrap_key(key, public_key)
+        assert sealed.startswith("sealed:")
+        assert sealed != sc.wrap_key(key, public_key)
+        blob = sc.encrypt_bytes(key, b"src")
+        sc.set_device_key(private_key)
+        assert bytes(sc.decrypt_bytes(sealed, blob)) == b"src"
+        # 封装的密钥同样可以用于加密
+        assert bytes(sc.decrypt_bytes(key, sc.encrypt_bytes(sealed, b"src"))) == b"src"
+
+    def test_requires_matching_device_key(self):
+        key = new_key()
+        _, public_key = sc.generate_device_key()
+        sealed = sc.wrap_key(key, public_key)
+        blob = sc.encrypt_bytes(key, b"src")
+        with pytest.raises(sc.InvalidKeyError):
+            sc.decrypt_bytes(sealed, blob)
+        other, _ = sc.generate_device_key()
+        sc.set_device_key(other)
+        with pytest.raises(sc.InvalidKeyError):
+            sc.decrypt_bytes(sealed, blob)
+
+    def test_tampered_sealed_key(self):
+        private_key, public_key = sc.generate_device_key()
+        sealed = sc.wrap_key(new_key(), public_key)
+        raw = base64.b64decode(sealed[len("sealed:") :])
+        sc.set_device_key(private_key)
+        with pytest.raises(sc.InvalidKeyError):
+            sc.decrypt_bytes("sealed:" + base64.b64encode(flip(raw, 40)).decode(), b"\0" * 64)